@@ -0,0 +1,388 @@
+//! Classifying tiles into biomes, and the biome composition of worlds for `biomes`
+
+use crate::reports::{BiomeReport, BiomeStat, LayerPercents};
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+use terraria_strings::TileId;
+use terraria_wld::{BaseHeader, Header, Layer, Tile, OCEAN_WIDTH};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Forest,
+    Desert,
+    Snow,
+    Jungle,
+    Corruption,
+    Crimson,
+    Hallow,
+    Mushroom,
+    Dungeon,
+    Underworld,
+    Ocean,
+}
+
+impl Biome {
+    pub const ALL: [Biome; 11] = [
+        Biome::Forest,
+        Biome::Desert,
+        Biome::Snow,
+        Biome::Jungle,
+        Biome::Corruption,
+        Biome::Crimson,
+        Biome::Hallow,
+        Biome::Mushroom,
+        Biome::Dungeon,
+        Biome::Underworld,
+        Biome::Ocean,
+    ];
+    pub fn name(self) -> &'static str {
+        match self {
+            Biome::Forest => "forest",
+            Biome::Desert => "desert",
+            Biome::Snow => "snow",
+            Biome::Jungle => "jungle",
+            Biome::Corruption => "corruption",
+            Biome::Crimson => "crimson",
+            Biome::Hallow => "hallow",
+            Biome::Mushroom => "mushroom",
+            Biome::Dungeon => "dungeon",
+            Biome::Underworld => "underworld",
+            Biome::Ocean => "ocean",
+        }
+    }
+}
+
+/// The biome a solid block belongs to, or `None` for anything that isn't a solid block.
+///
+/// Dungeon bricks and evil/hallowed blocks win over everything else, so their spread
+/// into other biomes is counted. Blocks that don't belong to any biome in particular,
+/// like dirt and stone, count as forest.
+pub fn classify(
+    tile: &Tile,
+    x: u16,
+    y: u16,
+    header: &Header,
+    base_header: &BaseHeader,
+) -> Option<Biome> {
+    let id = tile.front?;
+    if base_header.is_frame_important(id) {
+        return None;
+    }
+    Some(match TileId(id) {
+        TileId::BLUE_DUNGEON_BRICK | TileId::GREEN_DUNGEON_BRICK | TileId::PINK_DUNGEON_BRICK => {
+            Biome::Dungeon
+        }
+        TileId::CORRUPT_GRASS
+        | TileId::EBONSTONE
+        | TileId::CORRUPT_ICE
+        | TileId::EBONSAND
+        | TileId::CORRUPT_HARDENED_SAND
+        | TileId::CORRUPT_SANDSTONE => Biome::Corruption,
+        TileId::CRIMSON_GRASS
+        | TileId::CRIMSTONE
+        | TileId::FLESH_ICE
+        | TileId::CRIMSAND
+        | TileId::CRIMSON_HARDENED_SAND
+        | TileId::CRIMSON_SANDSTONE => Biome::Crimson,
+        TileId::HALLOWED_GRASS
+        | TileId::PEARLSTONE
+        | TileId::HALLOWED_ICE
+        | TileId::PEARLSAND
+        | TileId::HALLOW_HARDENED_SAND
+        | TileId::HALLOW_SANDSTONE => Biome::Hallow,
+        _ if header.layer(y) == Layer::Underworld => Biome::Underworld,
+        _ if header.layer(y) == Layer::Surface
+            && (x < OCEAN_WIDTH || x >= header.width.saturating_sub(OCEAN_WIDTH)) =>
+        {
+            Biome::Ocean
+        }
+        TileId::SAND | TileId::HARDENED_SAND | TileId::SANDSTONE | TileId::DESERT_FOSSIL => {
+            Biome::Desert
+        }
+        TileId::SNOW_BLOCK | TileId::ICE_BLOCK | TileId::BREAKABLE_ICE | TileId::SLUSH => {
+            Biome::Snow
+        }
+        TileId::MUD | TileId::JUNGLE_GRASS | TileId::HIVE | TileId::LIHZAHRD_BRICK => Biome::Jungle,
+        TileId::MUSHROOM_GRASS => Biome::Mushroom,
+        _ => Biome::Forest,
+    })
+}
+
+/// Size of the square cells the world is split into for [`BiomeMap`]
+const CELL_SIZE: u16 = 10;
+/// How many cells around a position count for its biome, in every direction
+const CELL_RADIUS: usize = 2;
+
+/// Biome block counts of the whole world, for looking up the biome of places like chests
+pub struct BiomeMap {
+    cells_wide: usize,
+    cells_high: usize,
+    counts: Vec<[u32; Biome::ALL.len()]>,
+}
+
+impl BiomeMap {
+    pub fn read(
+        file: &File,
+        base_header: &BaseHeader,
+        header: &Header,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut map = BiomeMap::empty(header);
+        terraria_wld::read_tiles(file, base_header, |tile, x, y| {
+            if let Some(biome) = classify(&tile, x, y, header, base_header) {
+                map.add(x, y, biome);
+            }
+        })?;
+        Ok(map)
+    }
+    fn empty(header: &Header) -> Self {
+        let cells_wide = usize::from(header.width.div_ceil(CELL_SIZE));
+        let cells_high = usize::from(header.height.div_ceil(CELL_SIZE));
+        BiomeMap {
+            cells_wide,
+            cells_high,
+            counts: vec![[0; Biome::ALL.len()]; cells_wide * cells_high],
+        }
+    }
+    /// Count a block of `biome` at a tile
+    fn add(&mut self, x: u16, y: u16, biome: Biome) {
+        let cell = usize::from(y / CELL_SIZE) * self.cells_wide + usize::from(x / CELL_SIZE);
+        self.counts[cell][biome as usize] += 1;
+    }
+    /// The biome of most blocks around a tile, `None` if there are no blocks around it
+    pub fn biome_around(&self, x: u16, y: u16) -> Option<Biome> {
+        let (cx, cy) = (usize::from(x / CELL_SIZE), usize::from(y / CELL_SIZE));
+        let mut sums = [0; Biome::ALL.len()];
+        for cy in cy.saturating_sub(CELL_RADIUS)..=(cy + CELL_RADIUS).min(self.cells_high - 1) {
+            for cx in cx.saturating_sub(CELL_RADIUS)..=(cx + CELL_RADIUS).min(self.cells_wide - 1) {
+                for (sum, n) in sums.iter_mut().zip(self.counts[cy * self.cells_wide + cx]) {
+                    *sum += n;
+                }
+            }
+        }
+        let (i, &max) = sums.iter().enumerate().max_by_key(|&(_, n)| n)?;
+        (max > 0).then_some(Biome::ALL[i])
+    }
+}
+
+/// Block counts of a biome, overall and split up
+#[derive(Default, Clone)]
+struct Counts {
+    total: u32,
+    layers: [u32; 4],
+    sections: Vec<u32>,
+}
+
+impl Counts {
+    fn new(n_sections: usize) -> Self {
+        Counts {
+            sections: vec![0; n_sections],
+            ..Default::default()
+        }
+    }
+}
+
+/// The share of each biome among the solid blocks of the world, also per layer and,
+/// if `n_sections` is given, per vertical strip of the world from west to east
+pub fn biome_composition(
+    path: &Path,
+    n_sections: Option<u16>,
+) -> Result<BiomeReport, Box<dyn Error>> {
+    let (file, base_header) = terraria_wld::open(path, false)?;
+    let header = terraria_wld::read_header(&file, base_header.offsets.header as u64)?;
+    let n_sections = n_sections.unwrap_or(0).min(header.width);
+    let section_width = match n_sections {
+        0 => header.width,
+        n => header.width.div_ceil(n),
+    };
+    let mut all = Counts::new(n_sections.into());
+    let mut biomes = vec![Counts::new(n_sections.into()); Biome::ALL.len()];
+    terraria_wld::read_tiles(&file, &base_header, |tile, x, y| {
+        let biome = match classify(&tile, x, y, &header, &base_header) {
+            Some(biome) => biome,
+            None => return,
+        };
+        let layer = header.layer(y) as usize;
+        let section = usize::from(x / section_width);
+        for counts in [&mut all, &mut biomes[biome as usize]] {
+            counts.total += 1;
+            counts.layers[layer] += 1;
+            if let Some(n) = counts.sections.get_mut(section) {
+                *n += 1;
+            }
+        }
+    })?;
+    let percent = |n: u32, of: u32| {
+        if of == 0 {
+            0.0
+        } else {
+            f64::from(n) / f64::from(of) * 100.0
+        }
+    };
+    let stats = Biome::ALL
+        .iter()
+        .zip(&biomes)
+        .map(|(biome, counts)| {
+            let layer = |layer: Layer| {
+                let i = layer as usize;
+                percent(counts.layers[i], all.layers[i])
+            };
+            BiomeStat {
+                biome: biome.name(),
+                blocks: counts.total,
+                percent: percent(counts.total, all.total),
+                layers: LayerPercents {
+                    surface: layer(Layer::Surface),
+                    underground: layer(Layer::Underground),
+                    cavern: layer(Layer::Cavern),
+                    underworld: layer(Layer::Underworld),
+                },
+                sections: counts
+                    .sections
+                    .iter()
+                    .zip(&all.sections)
+                    .map(|(&n, &of)| percent(n, of))
+                    .collect(),
+            }
+        })
+        .collect();
+    Ok(BiomeReport {
+        world: path.display().to_string(),
+        solid_blocks: all.total,
+        section_width: (n_sections > 0).then_some(section_width),
+        biomes: stats,
+    })
+}
+
+#[cfg(test)]
+fn test_base_header() -> BaseHeader {
+    let offsets = terraria_wld::Offsets {
+        header: 0,
+        tiles: 0,
+        chests: 0,
+        signs: 0,
+        npcs: 0,
+        entities: 0,
+        footer: 0,
+        unused_1: 0,
+        unused_2: 0,
+        unused_3: 0,
+        unknown_4: 0,
+    };
+    BaseHeader {
+        version: 279,
+        offsets,
+        times_saved: 0,
+        is_favorite: 0,
+        // Only Torch (4) stores its frame, which is enough to tell objects apart
+        tile_frame_important: vec![0b0001_0000],
+    }
+}
+
+/// Classify a block in a 1000x1000 world with the surface above 300, the caverns from
+/// 400 and the underworld from 800
+#[cfg(test)]
+fn classify_at(id: TileId, x: u16, y: u16) -> Option<Biome> {
+    let tile = Tile {
+        front: Some(id.0),
+        ..Default::default()
+    };
+    classify(
+        &tile,
+        x,
+        y,
+        &crate::test_header(1000, 1000),
+        &test_base_header(),
+    )
+}
+
+#[test]
+fn test_classify_plain_blocks() {
+    assert_eq!(classify_at(TileId::DIRT, 500, 100), Some(Biome::Forest));
+    assert_eq!(classify_at(TileId::SAND, 500, 100), Some(Biome::Desert));
+    assert_eq!(classify_at(TileId::MUD, 500, 500), Some(Biome::Jungle));
+    assert_eq!(classify_at(TileId::TORCHES, 500, 100), None);
+    let empty = Tile::default();
+    let header = crate::test_header(1000, 1000);
+    assert_eq!(
+        classify(&empty, 500, 100, &header, &test_base_header()),
+        None
+    );
+}
+
+#[test]
+fn test_classify_evil_over_location() {
+    // In the ocean band and in the underworld, spread and dungeons still count as such
+    assert_eq!(
+        classify_at(TileId::EBONSTONE, 5, 100),
+        Some(Biome::Corruption)
+    );
+    assert_eq!(
+        classify_at(TileId::CRIMSAND, 995, 100),
+        Some(Biome::Crimson)
+    );
+    assert_eq!(
+        classify_at(TileId::PEARLSTONE, 500, 900),
+        Some(Biome::Hallow)
+    );
+    assert_eq!(
+        classify_at(TileId::BLUE_DUNGEON_BRICK, 500, 900),
+        Some(Biome::Dungeon)
+    );
+}
+
+#[test]
+fn test_classify_ocean() {
+    let last = 1000 - 1;
+    assert_eq!(classify_at(TileId::SAND, 0, 100), Some(Biome::Ocean));
+    assert_eq!(
+        classify_at(TileId::SAND, OCEAN_WIDTH - 1, 100),
+        Some(Biome::Ocean)
+    );
+    assert_eq!(
+        classify_at(TileId::SAND, OCEAN_WIDTH, 100),
+        Some(Biome::Desert)
+    );
+    assert_eq!(classify_at(TileId::DIRT, last, 100), Some(Biome::Ocean));
+    assert_eq!(
+        classify_at(TileId::DIRT, 1000 - OCEAN_WIDTH, 100),
+        Some(Biome::Ocean)
+    );
+    assert_eq!(
+        classify_at(TileId::DIRT, 1000 - OCEAN_WIDTH - 1, 100),
+        Some(Biome::Forest)
+    );
+    // Only on the surface
+    assert_eq!(classify_at(TileId::SAND, 0, 300), Some(Biome::Desert));
+}
+
+#[test]
+fn test_classify_underworld() {
+    assert_eq!(classify_at(TileId::MUD, 500, 799), Some(Biome::Jungle));
+    assert_eq!(classify_at(TileId::MUD, 500, 800), Some(Biome::Underworld));
+    assert_eq!(classify_at(TileId::ASH, 0, 999), Some(Biome::Underworld));
+}
+
+#[test]
+fn test_biome_around() {
+    let mut map = BiomeMap::empty(&crate::test_header(1000, 1000));
+    assert_eq!(map.biome_around(500, 500), None);
+    for x in 500..510 {
+        map.add(x, 500, Biome::Jungle);
+    }
+    for x in 500..505 {
+        map.add(x, 510, Biome::Snow);
+    }
+    // The most common biome within two cells wins
+    assert_eq!(map.biome_around(500, 500), Some(Biome::Jungle));
+    assert_eq!(map.biome_around(520, 520), Some(Biome::Jungle));
+    assert_eq!(map.biome_around(530, 500), None);
+    for x in 500..520 {
+        map.add(x, 520, Biome::Snow);
+    }
+    assert_eq!(map.biome_around(500, 500), Some(Biome::Snow));
+    // Edges of the world don't go out of bounds
+    map.add(0, 0, Biome::Ocean);
+    assert_eq!(map.biome_around(0, 0), Some(Biome::Ocean));
+    assert_eq!(map.biome_around(999, 999), None);
+}
@@ -0,0 +1,304 @@
+//! Exporting chest contents to JSON/TOML for editing, and importing them back
+//!
+//! Items and prefixes are exported by their internal names, like `HermesBoots` and
+//! `Hasty2`, since display names aren't unique and change with the locale.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use terraria_strings::ItemIdMap;
+use terraria_wld::{Chest, ChestType, Item};
+
+/// The top level of an export file. TOML needs a table at the top, so the chests can't
+/// be at the root.
+#[derive(Serialize, Deserialize)]
+pub struct ChestFile {
+    pub chests: Vec<ExportedChest>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportedChest {
+    pub x: u16,
+    pub y: u16,
+    /// Only informative, ignored on import
+    #[serde(rename = "type", default, skip_serializing_if = "String::is_empty")]
+    pub type_: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub items: Vec<ExportedItem>,
+    /// Items from tModLoader's `.twld` file. Only informative, ignored on import, and
+    /// their slots can't be used by `items`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modded_items: Vec<ExportedItem>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportedItem {
+    pub slot: u8,
+    /// Internal name, or the id of items without one. Names are accepted on import too.
+    #[serde(alias = "name")]
+    pub item: String,
+    pub stack: u16,
+    /// Internal name of the prefix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+impl Format {
+    /// TOML for `.toml` files, JSON for everything else
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Format::Toml,
+            _ => Format::Json,
+        }
+    }
+}
+
+pub fn export(
+    chests: &[Chest],
+    chest_types: &HashMap<(u16, u16), ChestType>,
+    ids: &ItemIdMap,
+) -> ChestFile {
+    let chests = chests
+        .iter()
+        .map(|chest| ExportedChest {
+            x: chest.x,
+            y: chest.y,
+            type_: chest_types
+                .get(&(chest.x, chest.y))
+                .map(|type_| format!("{:?}", type_))
+                .unwrap_or_default(),
+            name: chest.name.clone(),
+            items: chest
+                .items
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| item.stack != 0)
+                .map(|(slot, item)| ExportedItem {
+                    slot: slot as u8,
+                    item: match ids.internal_name_by_id(item.id as u16) {
+                        Some(name) => name.to_owned(),
+                        None => item.id.to_string(),
+                    },
+                    stack: item.stack,
+                    prefix: crate::prefix_names::internal_name_by_id(item.prefix_id)
+                        .map(str::to_owned),
+                })
+                .collect(),
+            modded_items: chest
+                .modded_items
+                .iter()
+                .map(|item| ExportedItem {
+                    slot: item.slot,
+                    item: item.full_name(),
+                    stack: item.stack,
+                    prefix: item.mod_prefix.clone().or_else(|| {
+                        crate::prefix_names::internal_name_by_id(item.prefix_id).map(str::to_owned)
+                    }),
+                })
+                .collect(),
+        })
+        .collect();
+    ChestFile { chests }
+}
+
+pub fn to_string(file: &ChestFile, format: Format) -> Result<String, Box<dyn Error>> {
+    Ok(match format {
+        Format::Json => serde_json::to_string_pretty(file)?,
+        Format::Toml => toml::to_string(file)?,
+    })
+}
+
+pub fn from_str(text: &str, format: Format) -> Result<ChestFile, Box<dyn Error>> {
+    Ok(match format {
+        Format::Json => serde_json::from_str(text)?,
+        Format::Toml => toml::from_str(text)?,
+    })
+}
+
+/// Replace the contents of the chests listed in `file`.
+///
+/// Everything is validated before any chest is touched, so an invalid file leaves the
+/// chests as they were.
+/// Returns the number of chests changed.
+pub fn import(
+    file: &ChestFile,
+    chests: &mut [Chest],
+    ids: &ItemIdMap,
+) -> Result<usize, Box<dyn Error>> {
+    let mut updates = Vec::new();
+    for exported in &file.chests {
+        let context = |msg: String| format!("Chest at {}, {}: {}", exported.x, exported.y, msg);
+        let index = chests
+            .iter()
+            .position(|c| c.x == exported.x && c.y == exported.y)
+            .ok_or_else(|| context("No such chest in the world".into()))?;
+        // Names are written with a single length byte
+        if exported.name.len() > 126 {
+            return Err(context("Name is too long".into()).into());
+        }
+        let mut items = [Item::default(); 40];
+        for item in &exported.items {
+            let slot = items
+                .get_mut(usize::from(item.slot))
+                .ok_or_else(|| context(format!("Invalid slot {}", item.slot)))?;
+            if slot.stack != 0 {
+                return Err(context(format!("Slot {} is used twice", item.slot)).into());
+            }
+            if chests[index]
+                .modded_items
+                .iter()
+                .any(|m| m.slot == item.slot)
+            {
+                return Err(context(format!("Slot {} holds a modded item", item.slot)).into());
+            }
+            if item.stack == 0 {
+                return Err(context(format!("Empty stack of {}", item.item)).into());
+            }
+            // Exported unknown items are written as their ids. Internal names go first,
+            // since the names of some items are shared.
+            let id = match item.item.parse::<u16>() {
+                Ok(id) => id,
+                Err(_) => ids
+                    .id_by_internal_name(&item.item)
+                    .or_else(|| ids.id_by_name(&item.item))
+                    .ok_or_else(|| context(crate::unknown_item_msg(&item.item, ids)))?,
+            };
+            let prefix_id = match &item.prefix {
+                Some(prefix) => crate::prefix_names::id_by_internal_name(prefix)
+                    .ok_or_else(|| context(format!("Invalid prefix: {}", prefix)))?,
+                None => 0,
+            };
+            *slot = Item {
+                stack: item.stack,
+                id: i32::from(id),
+                prefix_id,
+            };
+        }
+        updates.push((index, exported.name.clone(), items));
+    }
+    let n_updated = updates.len();
+    for (index, name, items) in updates {
+        chests[index].name = name;
+        chests[index].items = items;
+    }
+    Ok(n_updated)
+}
+
+#[test]
+fn test_round_trip() {
+    let ids = terraria_strings::item_ids();
+    let mut chest = Chest {
+        x: 100,
+        y: 200,
+        name: "Loot".into(),
+        items: [Item::default(); 40],
+        modded_items: Vec::new(),
+    };
+    chest.items[2] = Item {
+        stack: 1,
+        id: terraria_strings::ItemId::HERMES_BOOTS.into(),
+        prefix_id: crate::prefix_names::id_by_name("Warding").unwrap(),
+    };
+    let mut chests = vec![chest.clone()];
+    for format in [Format::Json, Format::Toml] {
+        let text = to_string(&export(&chests, &HashMap::new(), ids), format).unwrap();
+        chests[0].items = [Item::default(); 40];
+        let file = from_str(&text, format).unwrap();
+        assert_eq!(import(&file, &mut chests, ids).unwrap(), 1);
+        let item = chests[0].items[2];
+        assert_eq!(
+            (item.id, item.prefix_id),
+            (chest.items[2].id, chest.items[2].prefix_id)
+        );
+    }
+    let bad = r#"{"chests": [{"x": 100, "y": 200, "items": [{"slot": 0, "name": "Hermes Boats", "stack": 1}]}]}"#;
+    let err = import(&from_str(bad, Format::Json).unwrap(), &mut chests, ids).unwrap_err();
+    assert!(err.to_string().contains("Hermes Boots"));
+}
+
+#[test]
+fn test_round_trip_shared_names() {
+    let ids = terraria_strings::item_ids();
+    // Every item whose name is shared with another one
+    let shared: Vec<u16> = ids
+        .iter()
+        .filter(|&(id, name)| ids.iter().any(|(other, n)| other != id && n == name))
+        .map(|(id, _)| id)
+        .collect();
+    assert!(shared.len() > 10);
+    let prefixes = [18, 75, 20, 43, 42, 76];
+    let original: Vec<Chest> = shared
+        .chunks(40)
+        .enumerate()
+        .map(|(n, chunk)| {
+            let mut chest = Chest {
+                x: 100 + n as u16 * 2,
+                y: 200,
+                name: String::new(),
+                items: [Item::default(); 40],
+                modded_items: Vec::new(),
+            };
+            for (slot, &id) in chunk.iter().enumerate() {
+                chest.items[slot] = Item {
+                    stack: 1,
+                    id: i32::from(id),
+                    prefix_id: prefixes[slot % prefixes.len()],
+                };
+            }
+            chest
+        })
+        .collect();
+    let mut chests = original.clone();
+    let key = |item: &Item| (item.id, item.prefix_id, item.stack);
+    for format in [Format::Json, Format::Toml] {
+        let text = to_string(&export(&chests, &HashMap::new(), ids), format).unwrap();
+        for chest in &mut chests {
+            chest.items = [Item::default(); 40];
+        }
+        import(&from_str(&text, format).unwrap(), &mut chests, ids).unwrap();
+        for (chest, orig) in chests.iter().zip(&original) {
+            assert!(chest.items.iter().map(key).eq(orig.items.iter().map(key)));
+        }
+    }
+}
+
+#[test]
+fn test_modded_slots() {
+    let ids = terraria_strings::item_ids();
+    let mut chests = vec![Chest {
+        x: 100,
+        y: 200,
+        name: String::new(),
+        items: [Item::default(); 40],
+        modded_items: vec![terraria_wld::tmod::ModdedItem {
+            slot: 0,
+            mod_name: "ExampleMod".into(),
+            name: "ExampleSword".into(),
+            stack: 1,
+            prefix_id: 0,
+            mod_prefix: None,
+        }],
+    }];
+    let exported = export(&chests, &HashMap::new(), ids);
+    assert_eq!(
+        exported.chests[0].modded_items[0].item,
+        "ExampleMod/ExampleSword"
+    );
+    let text = to_string(&exported, Format::Json).unwrap();
+    assert_eq!(
+        import(&from_str(&text, Format::Json).unwrap(), &mut chests, ids).unwrap(),
+        1
+    );
+    let bad = r#"{"chests": [{"x": 100, "y": 200, "items": [{"slot": 0, "name": "Torch", "stack": 1}]}]}"#;
+    let err = import(&from_str(bad, Format::Json).unwrap(), &mut chests, ids).unwrap_err();
+    assert!(err.to_string().contains("modded"));
+}
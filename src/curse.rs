@@ -0,0 +1,584 @@
+//! The `curse-chests` command, removing or replacing unwanted items in chests
+//!
+//! Rules are read from a file, one per line:
+//!
+//! ```text
+//! Magic Mirror
+//! @recall -> Lesser Healing Potion | Torch: gold, 2-5
+//! *Legendary
+//! ```
+//!
+//! The part before `->` says what to match: an item name, a `@category`, a `*Prefix`,
+//! or a prefix followed by an item or category. Without `->` matching items are removed,
+//! otherwise they're replaced by one of the items after it, picked at random. The
+//! segments after the colon take chest types and location constraints like in bless
+//! requirements, and a stack range for the replacement, which is otherwise a single
+//! item. Keeping the stack size of the replaced item could go over what the replacement
+//! stacks to.
+//!
+//! Items from tModLoader's `.twld` file are matched too, but only reported, since that
+//! file isn't rewritten.
+
+use crate::biomes::BiomeMap;
+use crate::reports::{ChestChange, CurseReport};
+use crate::req_file::{self, Location};
+use crate::{
+    chest_type_name, fits_location, modded_item_vanilla_id, prefix_names, read_chests_with_mods,
+};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use terraria_strings::{ItemId, ItemIdMap};
+use terraria_wld::ChestType;
+
+/// Groups of items a rule can match with `@category`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemCategory {
+    Potions,
+    /// Items that bring the player back to spawn
+    Recall,
+    Arrows,
+    Bullets,
+    Ores,
+    Bars,
+}
+
+impl FromStr for ItemCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_lowercase()[..] {
+            "potion" | "potions" => Ok(ItemCategory::Potions),
+            "recall" => Ok(ItemCategory::Recall),
+            "arrow" | "arrows" => Ok(ItemCategory::Arrows),
+            "bullet" | "bullets" => Ok(ItemCategory::Bullets),
+            "ore" | "ores" => Ok(ItemCategory::Ores),
+            "bar" | "bars" => Ok(ItemCategory::Bars),
+            _ => Err(format!(
+                "Unknown category '{}', expected potions, recall, arrows, bullets, ores or bars",
+                s
+            )),
+        }
+    }
+}
+
+const POTIONS: &[ItemId] = &[
+    ItemId::LESSER_HEALING_POTION,
+    ItemId::LESSER_MANA_POTION,
+    ItemId::HEALING_POTION,
+    ItemId::MANA_POTION,
+    ItemId::LESSER_RESTORATION_POTION,
+    ItemId::RESTORATION_POTION,
+    ItemId::OBSIDIAN_SKIN_POTION,
+    ItemId::REGENERATION_POTION,
+    ItemId::SWIFTNESS_POTION,
+    ItemId::GILLS_POTION,
+    ItemId::IRONSKIN_POTION,
+    ItemId::MANA_REGENERATION_POTION,
+    ItemId::MAGIC_POWER_POTION,
+    ItemId::FEATHERFALL_POTION,
+    ItemId::SPELUNKER_POTION,
+    ItemId::INVISIBILITY_POTION,
+    ItemId::SHINE_POTION,
+    ItemId::NIGHT_OWL_POTION,
+    ItemId::BATTLE_POTION,
+    ItemId::THORNS_POTION,
+    ItemId::WATER_WALKING_POTION,
+    ItemId::ARCHERY_POTION,
+    ItemId::HUNTER_POTION,
+    ItemId::GRAVITATION_POTION,
+    ItemId::GREATER_HEALING_POTION,
+    ItemId::GREATER_MANA_POTION,
+    ItemId::RED_POTION,
+    ItemId::SUPER_MANA_POTION,
+    ItemId::MINING_POTION,
+    ItemId::HEARTREACH_POTION,
+    ItemId::CALMING_POTION,
+    ItemId::BUILDER_POTION,
+    ItemId::TITAN_POTION,
+    ItemId::FLIPPER_POTION,
+    ItemId::SUMMONING_POTION,
+    ItemId::TRAPSIGHT_POTION,
+    ItemId::AMMO_RESERVATION_POTION,
+    ItemId::LIFEFORCE_POTION,
+    ItemId::ENDURANCE_POTION,
+    ItemId::RAGE_POTION,
+    ItemId::INFERNO_POTION,
+    ItemId::WRATH_POTION,
+    ItemId::RECALL_POTION,
+    ItemId::TELEPORTATION_POTION,
+    ItemId::LOVE_POTION,
+    ItemId::STINK_POTION,
+    ItemId::FISHING_POTION,
+    ItemId::SONAR_POTION,
+    ItemId::CRATE_POTION,
+    ItemId::WARMTH_POTION,
+    ItemId::GENDER_CHANGE_POTION,
+    ItemId::WORMHOLE_POTION,
+    ItemId::SUPER_HEALING_POTION,
+    ItemId::LUCK_POTION_LESSER,
+    ItemId::LUCK_POTION,
+    ItemId::LUCK_POTION_GREATER,
+    ItemId::POTION_OF_RETURN,
+];
+
+const RECALL: &[ItemId] = &[
+    ItemId::MAGIC_MIRROR,
+    ItemId::ICE_MIRROR,
+    ItemId::CELL_PHONE,
+    ItemId::RECALL_POTION,
+    ItemId::POTION_OF_RETURN,
+];
+
+const ARROWS: &[ItemId] = &[
+    ItemId::WOODEN_ARROW,
+    ItemId::FLAMING_ARROW,
+    ItemId::UNHOLY_ARROW,
+    ItemId::JESTERS_ARROW,
+    ItemId::HELLFIRE_ARROW,
+    ItemId::HOLY_ARROW,
+    ItemId::CURSED_ARROW,
+    ItemId::FROSTBURN_ARROW,
+    ItemId::CHLOROPHYTE_ARROW,
+    ItemId::ICHOR_ARROW,
+    ItemId::VENOM_ARROW,
+    ItemId::BONE_ARROW,
+    ItemId::MOONLORD_ARROW,
+];
+
+/// Ammo for guns, not all of which are called bullets
+const BULLETS: &[ItemId] = &[
+    ItemId::MUSKET_BALL,
+    ItemId::METEOR_SHOT,
+    ItemId::SILVER_BULLET,
+    ItemId::CRYSTAL_BULLET,
+    ItemId::CURSED_BULLET,
+    ItemId::CHLOROPHYTE_BULLET,
+    ItemId::HIGH_VELOCITY_BULLET,
+    ItemId::ICHOR_BULLET,
+    ItemId::VENOM_BULLET,
+    ItemId::PARTY_BULLET,
+    ItemId::NANO_BULLET,
+    ItemId::EXPLODING_BULLET,
+    ItemId::GOLDEN_BULLET,
+    ItemId::MOONLORD_BULLET,
+    ItemId::TUNGSTEN_BULLET,
+];
+
+const ORES: &[ItemId] = &[
+    ItemId::COPPER_ORE,
+    ItemId::TIN_ORE,
+    ItemId::IRON_ORE,
+    ItemId::LEAD_ORE,
+    ItemId::SILVER_ORE,
+    ItemId::TUNGSTEN_ORE,
+    ItemId::GOLD_ORE,
+    ItemId::PLATINUM_ORE,
+    ItemId::METEORITE,
+    ItemId::DEMONITE_ORE,
+    ItemId::CRIMTANE_ORE,
+    ItemId::HELLSTONE,
+    ItemId::COBALT_ORE,
+    ItemId::PALLADIUM_ORE,
+    ItemId::MYTHRIL_ORE,
+    ItemId::ORICHALCUM_ORE,
+    ItemId::ADAMANTITE_ORE,
+    ItemId::TITANIUM_ORE,
+    ItemId::CHLOROPHYTE_ORE,
+    ItemId::LUNAR_ORE,
+];
+
+const BARS: &[ItemId] = &[
+    ItemId::COPPER_BAR,
+    ItemId::TIN_BAR,
+    ItemId::IRON_BAR,
+    ItemId::LEAD_BAR,
+    ItemId::SILVER_BAR,
+    ItemId::TUNGSTEN_BAR,
+    ItemId::GOLD_BAR,
+    ItemId::PLATINUM_BAR,
+    ItemId::METEORITE_BAR,
+    ItemId::DEMONITE_BAR,
+    ItemId::CRIMTANE_BAR,
+    ItemId::HELLSTONE_BAR,
+    ItemId::COBALT_BAR,
+    ItemId::PALLADIUM_BAR,
+    ItemId::MYTHRIL_BAR,
+    ItemId::ORICHALCUM_BAR,
+    ItemId::ADAMANTITE_BAR,
+    ItemId::TITANIUM_BAR,
+    ItemId::HALLOWED_BAR,
+    ItemId::CHLOROPHYTE_BAR,
+    ItemId::SHROOMITE_BAR,
+    ItemId::SPECTRE_BAR,
+    ItemId::LUNAR_BAR,
+];
+
+impl ItemCategory {
+    fn contains(self, id: u16) -> bool {
+        let items = match self {
+            ItemCategory::Potions => POTIONS,
+            ItemCategory::Recall => RECALL,
+            ItemCategory::Arrows => ARROWS,
+            ItemCategory::Bullets => BULLETS,
+            ItemCategory::Ores => ORES,
+            ItemCategory::Bars => BARS,
+        };
+        items.contains(&ItemId(id))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ItemMatch {
+    Any,
+    Item(u16),
+    Category(ItemCategory),
+}
+
+#[derive(Debug, PartialEq)]
+struct Rule {
+    item: ItemMatch,
+    prefix_id: Option<u8>,
+    /// Empty to remove the item
+    replace_with: Vec<u16>,
+    stack_range: Option<(u16, u16)>,
+    /// Empty for all chest types
+    only_in: Vec<ChestType>,
+    location: Location,
+}
+
+impl Rule {
+    fn parse(line: &str, ids: &ItemIdMap) -> Result<Self, Box<dyn Error>> {
+        let (line, segments) = match line.split_once(':') {
+            Some((line, segments)) => (line, req_file::parse_segments(segments)?),
+            None => (line, req_file::Segments::default()),
+        };
+        if segments.n_stacks.is_some() {
+            return Err("Number of stacks doesn't apply to curse rules".into());
+        }
+        if segments.location.spacing.is_some() {
+            return Err("Spacing doesn't apply to curse rules".into());
+        }
+        if segments.weight.is_some() {
+            return Err("Weight doesn't apply to curse rules".into());
+        }
+        let (matcher, replacement) = match line.split_once("->") {
+            Some((matcher, replacement)) => (matcher.trim(), Some(replacement)),
+            None => (line.trim(), None),
+        };
+        let item_id = |name: &str| {
+            let name = name.trim();
+            ids.id_by_any_name(name)
+                .ok_or_else(|| crate::unknown_item_msg(name, ids))
+        };
+        let (prefix_id, item) = req_file::split_prefix(matcher)?;
+        let item = if item.is_empty() {
+            if prefix_id.is_none() {
+                return Err("Expected an item, @category or *Prefix".into());
+            }
+            ItemMatch::Any
+        } else if let Some(category) = item.strip_prefix('@') {
+            ItemMatch::Category(category.parse()?)
+        } else {
+            ItemMatch::Item(item_id(item)?)
+        };
+        let replace_with = match replacement {
+            Some(replacement) => replacement
+                .split('|')
+                .map(item_id)
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+        if replace_with.is_empty() && segments.stack_range.is_some() {
+            return Err("A stack range needs something to replace the item with".into());
+        }
+        Ok(Rule {
+            item,
+            prefix_id,
+            replace_with,
+            stack_range: segments.stack_range,
+            only_in: segments.only_in.unwrap_or_default(),
+            location: segments.location,
+        })
+    }
+    /// `id` is `None` for modded items, which only match rules for any item
+    fn matches(&self, id: Option<u16>, prefix_id: u8) -> bool {
+        let item_matches = match (&self.item, id) {
+            (ItemMatch::Any, _) => true,
+            (&ItemMatch::Item(rule_id), Some(id)) => id == rule_id,
+            (&ItemMatch::Category(category), Some(id)) => category.contains(id),
+            (_, None) => false,
+        };
+        item_matches && self.prefix_id.is_none_or(|prefix| prefix == prefix_id)
+    }
+}
+
+fn parse_rules(txt: &str, ids: &ItemIdMap) -> Result<Vec<Rule>, Box<dyn Error>> {
+    let mut rules = Vec::new();
+    for (n, line) in txt.lines().enumerate() {
+        let line = line.trim();
+        if !(line.is_empty() || line.starts_with('#')) {
+            rules.push(Rule::parse(line, ids).map_err(|e| format!("Line {}: {}", n + 1, e))?);
+        }
+    }
+    Ok(rules)
+}
+
+/// Remove or replace the items matching the rules in the world's chests. The first
+/// matching rule of an item is used. With `dry_run`, the world isn't changed.
+pub fn curse_chests(
+    rules_path: &Path,
+    world_path: &Path,
+    seed: u64,
+    dry_run: bool,
+) -> Result<CurseReport, Box<dyn Error>> {
+    let ids = terraria_strings::item_ids();
+    let rules = parse_rules(&fs::read_to_string(rules_path)?, ids)?;
+    let (file, mut base_header) = terraria_wld::open(world_path, !dry_run)?;
+    let mut chests = read_chests_with_mods(world_path, &file, &base_header)?;
+    let header = terraria_wld::read_header(&file, base_header.offsets.header as u64)?;
+    let chest_types = terraria_wld::read_chest_types(&file, &base_header)?;
+    let biome_map = if rules.iter().any(|rule| !rule.location.biomes.is_empty()) {
+        Some(BiomeMap::read(&file, &base_header, &header)?)
+    } else {
+        None
+    };
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let changes = apply_rules(
+        &rules,
+        &mut chests,
+        &chest_types,
+        &header,
+        biome_map.as_ref(),
+        &mut rng,
+    );
+    if !dry_run {
+        terraria_wld::write_chests(&file, &mut base_header, &chests)?;
+    }
+    Ok(CurseReport {
+        world: world_path.display().to_string(),
+        seed,
+        dry_run,
+        changes,
+    })
+}
+
+/// Remove or replace the matching items in the chests, returning what was changed
+fn apply_rules(
+    rules: &[Rule],
+    chests: &mut [terraria_wld::Chest],
+    chest_types: &HashMap<(u16, u16), ChestType>,
+    header: &terraria_wld::Header,
+    biome_map: Option<&BiomeMap>,
+    rng: &mut impl Rng,
+) -> Vec<ChestChange> {
+    let ids = terraria_strings::item_ids();
+    let mut changes = Vec::new();
+    for chest in chests {
+        // Chests on tiles that aren't read, like modded ones, only match rules for any
+        // chest type
+        let chest_type = chest_types.get(&(chest.x, chest.y)).copied();
+        // Which rules apply doesn't depend on the item, so check the chest only once
+        let rules: Vec<&Rule> = rules
+            .iter()
+            .filter(|rule| {
+                (rule.only_in.is_empty()
+                    || chest_type.is_some_and(|type_| rule.only_in.contains(&type_)))
+                    && fits_location(chest, &rule.location, header, biome_map)
+            })
+            .collect();
+        for item in &mut chest.items {
+            if item.stack == 0 {
+                continue;
+            }
+            let id = u16::try_from(item.id).ok();
+            let rule = match rules.iter().find(|rule| rule.matches(id, item.prefix_id)) {
+                Some(rule) => rule,
+                None => continue,
+            };
+            let name = |id: i32| {
+                ids.name_by_id(id as u16)
+                    .map_or_else(|| format!("unknown({})", id), str::to_owned)
+            };
+            let mut change = ChestChange {
+                item: name(item.id),
+                prefix: prefix_names::name_by_id(item.prefix_id).map(str::to_owned),
+                stack: item.stack,
+                replacement: None,
+                replacement_stack: 0,
+                x: chest.x,
+                y: chest.y,
+                chest_type: chest_type_name(chest_type),
+                gps: header.tile_to_gps_pos(chest.x, chest.y).to_string(),
+                modded: false,
+            };
+            match rule.replace_with.choose(rng) {
+                Some(&id) => {
+                    item.id = i32::from(id);
+                    item.prefix_id = 0;
+                    item.stack = match rule.stack_range {
+                        Some((min, max)) => rng.gen_range(min..=max),
+                        None => 1,
+                    };
+                    change.replacement = Some(name(item.id));
+                    change.replacement_stack = item.stack;
+                }
+                None => *item = Default::default(),
+            }
+            changes.push(change);
+        }
+        for item in &chest.modded_items {
+            let id = modded_item_vanilla_id(item, ids);
+            if !rules.iter().any(|rule| rule.matches(id, item.prefix_id)) {
+                continue;
+            }
+            changes.push(ChestChange {
+                item: match id.and_then(|id| ids.name_by_id(id)) {
+                    Some(name) => name.to_owned(),
+                    None => item.full_name(),
+                },
+                prefix: item
+                    .mod_prefix
+                    .clone()
+                    .or_else(|| prefix_names::name_by_id(item.prefix_id).map(str::to_owned)),
+                stack: item.stack,
+                replacement: None,
+                replacement_stack: 0,
+                x: chest.x,
+                y: chest.y,
+                chest_type: chest_type_name(chest_type),
+                gps: header.tile_to_gps_pos(chest.x, chest.y).to_string(),
+                modded: true,
+            });
+        }
+    }
+    changes
+}
+
+#[test]
+fn test_parse_rules() {
+    let ids = terraria_strings::item_ids();
+    let rules = parse_rules(
+        "# Comment\n\
+         Magic Mirror\n\
+         @recall -> Lesser Healing Potion | Torch: gold, 2-5, layer=surface\n\
+         *Legendary\n",
+        ids,
+    )
+    .unwrap();
+    assert_eq!(rules.len(), 3);
+    assert_eq!(rules[0].item, ItemMatch::Item(ItemId::MAGIC_MIRROR.0));
+    assert!(rules[0].replace_with.is_empty());
+    assert_eq!(rules[1].item, ItemMatch::Category(ItemCategory::Recall));
+    assert_eq!(
+        rules[1].replace_with,
+        [ItemId::LESSER_HEALING_POTION.0, ItemId::TORCH.0]
+    );
+    assert_eq!(rules[1].stack_range, Some((2, 5)));
+    assert_eq!(rules[1].only_in, [ChestType::Gold]);
+    assert_eq!(rules[2].item, ItemMatch::Any);
+    assert!(rules[2].prefix_id.is_some());
+    assert!(parse_rules("Magic Mirror: 3", ids).is_err());
+    assert!(parse_rules("Magic Mirror: 2-5", ids).is_err());
+}
+
+#[test]
+fn test_categories() {
+    assert!(ItemCategory::Potions.contains(ItemId::RECALL_POTION.0));
+    assert!(ItemCategory::Potions.contains(ItemId::POTION_OF_RETURN.0));
+    assert!(ItemCategory::Recall.contains(ItemId::POTION_OF_RETURN.0));
+    assert!(!ItemCategory::Potions.contains(ItemId::POTION_STATUE.0));
+    assert!(ItemCategory::Bullets.contains(ItemId::MUSKET_BALL.0));
+    assert!(ItemCategory::Bullets.contains(ItemId::METEOR_SHOT.0));
+    assert!(!ItemCategory::Bullets.contains(ItemId::EMPTY_BULLET.0));
+    assert!(ItemCategory::Ores.contains(ItemId::HELLSTONE.0));
+    assert!(ItemCategory::Ores.contains(ItemId::METEORITE.0));
+    assert!(ItemCategory::Bars.contains(ItemId::HELLSTONE_BAR.0));
+    assert!(!ItemCategory::Bars.contains(ItemId::BAR_STOOL.0));
+    assert!(!ItemCategory::Arrows.contains(ItemId::WOODEN_BOW.0));
+    assert!(!ItemCategory::Arrows.contains(ItemId::ARROW_SIGN.0));
+}
+
+#[test]
+fn test_apply_rules() {
+    use rand::rngs::mock::StepRng;
+    use terraria_wld::Item;
+    let ids = terraria_strings::item_ids();
+    let rules = parse_rules(
+        "Magic Mirror\n\
+         @arrows -> Torch: gold, 3-3\n\
+         *Legendary\n",
+        ids,
+    )
+    .unwrap();
+    let legendary = prefix_names::id_by_name("Legendary").unwrap();
+    let item = |id: ItemId, stack, prefix_id| Item {
+        stack,
+        id: i32::from(id),
+        prefix_id,
+    };
+    let mut chests = vec![
+        crate::test_chest(0, 0),
+        crate::test_chest(10, 0),
+        crate::test_chest(20, 0),
+    ];
+    chests[0].items[..4].copy_from_slice(&[
+        item(ItemId::MAGIC_MIRROR, 1, 0),
+        item(ItemId::WOODEN_ARROW, 50, 0),
+        item(ItemId::MUSKET_BALL, 50, 0),
+        item(ItemId::WOODEN_SWORD, 1, legendary),
+    ]);
+    chests[1].items[0] = item(ItemId::WOODEN_ARROW, 50, 0);
+    chests[2].items[..2].copy_from_slice(&[
+        item(ItemId::MAGIC_MIRROR, 1, 0),
+        item(ItemId::WOODEN_ARROW, 50, 0),
+    ]);
+    // The third chest is on a tile whose chest types aren't read
+    let chest_types = HashMap::from([((0, 100), ChestType::Gold), ((10, 100), ChestType::Plain)]);
+    let header = crate::test_header(1000, 1000);
+    let changes = apply_rules(
+        &rules,
+        &mut chests,
+        &chest_types,
+        &header,
+        None,
+        &mut StepRng::new(0, 1),
+    );
+    let key = |item: &Item| (item.id, item.stack, item.prefix_id);
+    let torch = i32::from(ItemId::TORCH);
+    let musket_ball = i32::from(ItemId::MUSKET_BALL);
+    let arrow = i32::from(ItemId::WOODEN_ARROW);
+    assert_eq!(
+        chests[0].items[..4].iter().map(key).collect::<Vec<_>>(),
+        [(0, 0, 0), (torch, 3, 0), (musket_ball, 50, 0), (0, 0, 0)]
+    );
+    assert_eq!(key(&chests[1].items[0]), (arrow, 50, 0));
+    assert_eq!(
+        chests[2].items[..2].iter().map(key).collect::<Vec<_>>(),
+        [(0, 0, 0), (arrow, 50, 0)]
+    );
+    let changes: Vec<_> = changes
+        .iter()
+        .map(|c| {
+            (
+                c.x,
+                c.item.as_str(),
+                c.replacement.as_deref(),
+                c.chest_type.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        changes,
+        [
+            (0, "Magic Mirror", None, "Gold"),
+            (0, "Wooden Arrow", Some("Torch"), "Gold"),
+            (0, "Wooden Sword", None, "Gold"),
+            (20, "Magic Mirror", None, "Unknown"),
+        ]
+    );
+}
@@ -1,35 +1,121 @@
-use ansi_term::Colour::{Green, Red};
-use clap::Parser;
-use rand::{rngs::ThreadRng, seq::SliceRandom, thread_rng, Rng};
-use std::collections::HashMap;
+use clap::{Parser, Subcommand};
+use rand::{seq::SliceRandom, thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fs::File;
 use std::io::{self, prelude::*};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
+mod biomes;
+mod chest_export;
+mod curse;
+mod output;
 mod prefix_names;
+mod render;
+mod reports;
 mod req_file;
+mod summary_cache;
+mod tile_stats;
+
+use output::Printer;
+use reports::*;
+use summary_cache::{SummaryCache, WorldSummary};
 
 #[derive(Parser)]
 #[clap(about, version)]
 /// Terraria world inspection/manupilation tool
-enum Args {
+struct Args {
+    /// Language to use for item names, like de-DE or pt-BR
+    #[clap(long, global = true)]
+    locale: Option<String>,
+    /// Directory containing Terraria localization files, named like de-DE.json.
+    /// Defaults to the `locales` directory next to the wldmania executable.
+    ///
+    /// The files ship inside Terraria.exe as embedded resources named like
+    /// Terraria.Localization.Content.de-DE.Items.json. Extract them with a .NET resource
+    /// extractor, like ILSpy, and rename them to de-DE.json.
+    #[clap(long, global = true)]
+    locale_dir: Option<PathBuf>,
+    /// Output format: text, json or csv.
+    /// Supported by info, itemhunt, bless-chests, curse-chests, find, find-tile, structures,
+    /// chest-info, analyze-chests, biomes and tile-stats.
+    #[clap(long, global = true, default_value = "text")]
+    format: output::Format,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show basic facts about worlds, like seed, size and game mode
+    Info {
+        /// Paths to terraria .wld files to look at
+        #[clap(required = true)]
+        world_paths: Vec<PathBuf>,
+    },
     /// Check if world(s) contain the desired items
     Itemhunt {
-        /// File containing the list of desired items
+        /// File containing the list of desired items. A line like
+        /// "Hermes Boots | Flurry Boots: 2" is met by two stacks of any of them.
+        /// Requirements count for their weight= (1 by default) towards the score of a world,
+        /// partly met ones for part of it.
         req_path: PathBuf,
+        /// Only show the N best scoring worlds, ranked, followed by a summary table
+        #[clap(long)]
+        top: Option<usize>,
+        /// File to keep chest summaries of the worlds in, so worlds that didn't change
+        /// since the last hunt don't need to be read again
+        #[clap(long)]
+        cache: Option<PathBuf>,
         /// Paths to terraria .wld files to search
         #[clap(required = true)]
         world_paths: Vec<PathBuf>,
     },
     /// Bless the chests in the world with the desired items
     BlessChests {
-        /// File containing the list of desired items
+        /// File containing the list of desired items. Besides chest types, items can be
+        /// restricted to places with layer=, biome=, side= (of spawn), min-distance=,
+        /// max-distance= (from spawn) and spacing= (from other placed stacks) segments.
+        /// Every stack of a line like "Hermes Boots | Flurry Boots" is one of them picked at
+        /// random.
         req_path: PathBuf,
+        /// Seed for choosing the chests and stack sizes. The same seed, requirements and
+        /// world always give the same result. A random one is used and shown if not given.
+        #[clap(long)]
+        seed: Option<u64>,
+        /// Only show where the items would go, without changing the worlds
+        #[clap(long)]
+        dry_run: bool,
+        /// What to do when no chest can take an item, because they're all full or there
+        /// are none: "fail" without changing the world, or "skip" the item and list it in
+        /// the report
+        #[clap(long, default_value = "fail")]
+        on_full: OnFull,
         /// Paths to terraria .wld files to search
         #[clap(required = true)]
         world_paths: Vec<PathBuf>,
     },
+    /// Remove or replace unwanted items in the chests of the world
+    #[clap(alias = "strip-chests")]
+    CurseChests {
+        /// File with one rule per line, like "Magic Mirror", "@recall -> Torch | Glowstick:
+        /// gold, 5-10" or "*Legendary". Rules take the same chest types and location
+        /// constraints as bless-chests requirements, and replacements are separated by "|"
+        /// like bless-chests items.
+        rules_path: PathBuf,
+        /// Seed for picking replacements. A random one is used and shown if not given.
+        #[clap(long)]
+        seed: Option<u64>,
+        /// Only show what would be removed or replaced, without changing the worlds
+        #[clap(long)]
+        dry_run: bool,
+        /// Paths to terraria .wld files to change
+        #[clap(required = true)]
+        world_paths: Vec<PathBuf>,
+    },
     /// Find an item in the world
     Find {
         /// Name of the item to find
@@ -38,6 +124,50 @@ enum Args {
         #[clap(required = true)]
         world_paths: Vec<PathBuf>,
     },
+    /// Find tiles or objects in the world, like Life Crystals or Demon Altars
+    FindTile {
+        /// Name or id of the tile to find
+        tile: String,
+        /// Only match objects of this style, like 1 for Gold Chests
+        #[clap(long)]
+        style: Option<u16>,
+        /// Only match tiles with this horizontal frame offset.
+        /// For objects, this is the offset of their top left tile.
+        #[clap(long)]
+        frame_x: Option<u16>,
+        /// Only match tiles with this vertical frame offset
+        #[clap(long)]
+        frame_y: Option<u16>,
+        /// Paths to terraria .wld files to search
+        #[clap(required = true)]
+        world_paths: Vec<PathBuf>,
+    },
+    /// Find structures like the dungeon, floating islands, pyramids and living trees
+    Structures {
+        /// Paths to terraria .wld files to search
+        #[clap(required = true)]
+        world_paths: Vec<PathBuf>,
+    },
+    /// Render a world to a PNG image, one pixel per tile
+    Render {
+        /// Path to a Terraria .wld file to render
+        world_path: PathBuf,
+        /// Path of the PNG image to write
+        #[clap(short, long)]
+        output: PathBuf,
+        /// Scale of the image, like 0.25 for a quarter of the size or 4 for 4 pixels per tile
+        #[clap(long, default_value = "1")]
+        scale: f64,
+        /// Only render this part of the world, given as x,y,width,height in tiles
+        #[clap(long, parse(try_from_str = render::parse_region))]
+        region: Option<terraria_render::Region>,
+        /// What to draw of each tile: tiles, walls, liquids and wires
+        #[clap(long, default_value = "tiles,walls,liquids", parse(try_from_str = render::parse_layers))]
+        layers: terraria_render::Layers,
+        /// Markers to draw over the world: chests, spawn and npcs
+        #[clap(long, use_value_delimiter = true)]
+        overlay: Vec<render::Overlay>,
+    },
     /// Fix NPCs that disappeared due to the NaN position bug.
     FixNpcs {
         /// Paths to terraria .wld files to fix
@@ -59,18 +189,68 @@ enum Args {
         /// Y position of chest
         y: u16,
     },
-    /// Show the corruption/crimson percentage of worlds
-    CorruptionPercent {
+    /// Print the contents of every chest as JSON (or TOML), for editing with import-chests
+    ExportChests {
+        /// Path to a Terraria .wld file to export from
+        world_path: PathBuf,
+        /// Print TOML instead of JSON
+        #[clap(long)]
+        toml: bool,
+    },
+    /// Replace the contents of chests with the ones in an exported file
+    ImportChests {
+        /// Path to a Terraria .wld file to modify
+        world_path: PathBuf,
+        /// File produced by export-chests. Read as TOML if it ends with .toml, JSON otherwise.
+        chests_path: PathBuf,
+    },
+    /// Show the gear, inventory and banks of players
+    PlayerInfo {
+        /// Paths to terraria .plr files to look at
+        #[clap(required = true)]
+        player_paths: Vec<PathBuf>,
+    },
+    /// Show which share of the solid blocks of worlds belongs to which biome,
+    /// like forest, jungle or corruption
+    #[clap(alias = "corruption-percent")]
+    Biomes {
+        /// Also split the world into this many sections from west to east,
+        /// and show the biomes of each
+        #[clap(long)]
+        sections: Option<u16>,
         /// Paths to terraria .wld files to analyze
         #[clap(required = true)]
         world_paths: Vec<PathBuf>,
     },
-    /// Count the ores in the given worlds
-    CountOres {
+    /// Count every tile, wall and liquid in the given worlds, and show which
+    /// ore alternatives they have
+    #[clap(alias = "count-ores")]
+    TileStats {
+        /// Only count tiles in these categories: ores, gems, bars or blocks
+        #[clap(long, use_value_delimiter = true)]
+        only: Vec<tile_stats::Category>,
+        /// Show the counts for each layer: surface, underground, cavern and underworld
+        #[clap(long)]
+        layers: bool,
         /// Paths to terraria .wld files to analyze
         #[clap(required = true)]
         world_paths: Vec<PathBuf>,
     },
+    /// List the chests a player hasn't discovered yet on their map
+    UndiscoveredChests {
+        /// Path to a Terraria .wld file to look at
+        world_path: PathBuf,
+        /// Path to the player's .plr file, or directly to a .map file
+        player_or_map_path: PathBuf,
+    },
+    /// Search for items by name, listing the closest matches with their ids
+    SearchItems {
+        /// (Part of) the name of the item
+        query: String,
+        /// Maximum number of matches to show
+        #[clap(short, long, default_value = "20")]
+        limit: usize,
+    },
     /// Generate template requirements file
     GenReq {
         /// Path to write the template file to
@@ -78,54 +258,182 @@ enum Args {
     },
 }
 
+impl Command {
+    /// Whether the command's output goes through a [`output::Report`], so it can be
+    /// printed in every `--format`
+    fn has_report(&self) -> bool {
+        !matches!(
+            self,
+            Command::Render { .. }
+                | Command::FixNpcs { .. }
+                | Command::ExportChests { .. }
+                | Command::ImportChests { .. }
+                | Command::PlayerInfo { .. }
+                | Command::UndiscoveredChests { .. }
+                | Command::SearchItems { .. }
+                | Command::GenReq { .. }
+        )
+    }
+}
+
 fn run() -> Result<(), Box<dyn Error>> {
-    match Args::parse() {
-        Args::Itemhunt {
+    let args = Args::parse();
+    if args.format != output::Format::Text && !args.command.has_report() {
+        return Err(
+            "This command only prints text. --format is supported by info, \
+                    itemhunt, bless-chests, curse-chests, find, find-tile, structures, \
+                    chest-info, analyze-chests, biomes and tile-stats."
+                .into(),
+        );
+    }
+    if let Some(locale) = &args.locale {
+        load_locale(locale, args.locale_dir.as_deref())?;
+    }
+    let mut printer = Printer::new(args.format);
+    match args.command {
+        Command::Info { world_paths } => {
+            for path in world_paths {
+                printer.print(&world_info(&path)?)?;
+            }
+        }
+        Command::Itemhunt {
             req_path,
+            top,
+            cache,
             world_paths,
         } => {
-            itemhunt(&req_path, &world_paths)?;
+            itemhunt(&req_path, &world_paths, top, cache.as_deref(), &mut printer)?;
         }
-        Args::BlessChests {
+        Command::BlessChests {
             req_path,
+            seed,
+            dry_run,
+            on_full,
             world_paths,
         } => {
+            let seed = seed.unwrap_or_else(|| thread_rng().gen());
             for world in world_paths {
-                bless_chests(&req_path, &world)?;
+                let report = bless_chests(&req_path, &world, seed, dry_run, on_full)?;
+                printer.print(&report)?;
             }
         }
-        Args::Find {
+        Command::CurseChests {
+            rules_path,
+            seed,
+            dry_run,
+            world_paths,
+        } => {
+            let seed = seed.unwrap_or_else(|| thread_rng().gen());
+            for world in world_paths {
+                printer.print(&curse::curse_chests(&rules_path, &world, seed, dry_run)?)?;
+            }
+        }
+        Command::Find {
             world_paths,
             item_name,
         } => {
             for path in world_paths {
-                find_item(&path, &item_name)?;
+                printer.print(&find_item(&path, &item_name)?)?;
+            }
+        }
+        Command::FindTile {
+            tile,
+            style,
+            frame_x,
+            frame_y,
+            world_paths,
+        } => {
+            let id = match tile.parse() {
+                Ok(id) => id,
+                Err(_) => {
+                    let ids = terraria_strings::tile_ids();
+                    ids.id_by_any_name(&tile)
+                        .ok_or_else(|| unknown_name_msg("tile", &tile, ids))?
+                }
+            };
+            for path in world_paths {
+                let filter = TileFilter {
+                    frame_x,
+                    frame_y,
+                    style,
+                };
+                printer.print(&find_tile(&path, id, &filter)?)?;
             }
         }
-        Args::FixNpcs { world_paths } => {
+        Command::Structures { world_paths } => {
+            for path in world_paths {
+                printer.print(&find_structures(&path)?)?;
+            }
+        }
+        Command::Render {
+            world_path,
+            output,
+            scale,
+            region,
+            layers,
+            overlay,
+        } => {
+            render::render(&world_path, &output, scale, region, &layers, &overlay)?;
+        }
+        Command::FixNpcs { world_paths } => {
             for path in world_paths {
                 fix_npcs(&path)?;
             }
         }
-        Args::AnalyzeChests { world_paths } => {
+        Command::AnalyzeChests { world_paths } => {
             for path in world_paths {
-                analyze_chests(&path)?;
+                printer.print(&analyze_chests(&path)?)?;
             }
         }
-        Args::ChestInfo { world_path, x, y } => {
-            chest_info(&world_path, x, y)?;
+        Command::ChestInfo { world_path, x, y } => {
+            printer.print(&chest_info(&world_path, x, y)?)?;
+        }
+        Command::ExportChests { world_path, toml } => {
+            let format = if toml {
+                chest_export::Format::Toml
+            } else {
+                chest_export::Format::Json
+            };
+            export_chests(&world_path, format)?;
         }
-        Args::CorruptionPercent { world_paths } => {
+        Command::ImportChests {
+            world_path,
+            chests_path,
+        } => {
+            import_chests(&world_path, &chests_path)?;
+        }
+        Command::PlayerInfo { player_paths } => {
+            for path in player_paths {
+                player_info(&path)?;
+            }
+        }
+        Command::Biomes {
+            sections,
+            world_paths,
+        } => {
             for path in world_paths {
-                corruption_percent(&path)?;
+                printer.print(&biomes::biome_composition(&path, sections)?)?;
             }
         }
-        Args::CountOres { world_paths } => {
+        Command::TileStats {
+            only,
+            layers,
+            world_paths,
+        } => {
             for path in world_paths {
-                count_ores(&path)?;
+                printer.print(&tile_stats::tile_stats(&path, &only, layers)?)?;
             }
         }
-        Args::GenReq { path } => {
+        Command::UndiscoveredChests {
+            world_path,
+            player_or_map_path,
+        } => {
+            undiscovered_chests(&world_path, &player_or_map_path)?;
+        }
+        Command::SearchItems { query, limit } => {
+            search_items(&query, limit);
+        }
+        Command::GenReq { path } => {
             generate_template_cfg(&path)?;
         }
     }
@@ -138,27 +446,281 @@ fn main() {
     }
 }
 
-fn chest_info(wld_path: &Path, x: u16, y: u16) -> Result<(), Box<dyn Error>> {
+/// Load the item names of a locale from `dir`, or from the `locales` directory next to the
+/// executable
+fn load_locale(code: &str, dir: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let locale = terraria_strings::Locale::from_code(code)
+        .ok_or_else(|| format!("Unknown locale: {}", code))?;
+    if locale == terraria_strings::Locale::English {
+        return Ok(());
+    }
+    let dir = match dir {
+        Some(dir) => dir.to_owned(),
+        None => {
+            let exe = std::env::current_exe()?;
+            exe.parent().unwrap_or(Path::new(".")).join("locales")
+        }
+    };
+    let path = dir.join(format!("{}.json", locale.code()));
+    if !path.exists() {
+        return Err(format!(
+            "Localization file {} not found. Extract \
+             Terraria.Localization.Content.{}.Items.json from Terraria.exe (with ILSpy, for \
+             example), save it as {}.json, and point --locale-dir to its directory",
+            path.display(),
+            locale.code(),
+            locale.code()
+        )
+        .into());
+    }
+    let json = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Couldn't read localization file {}: {}", path.display(), e))?;
+    terraria_strings::set_item_localization(&json)
+}
+
+fn world_info(path: &Path) -> Result<InfoReport, Box<dyn Error>> {
+    let (file, base_header) = terraria_wld::open(path, false)?;
+    let header = terraria_wld::read_header(&file, base_header.offsets.header as u64)?;
+    Ok(InfoReport {
+        world: path.display().to_string(),
+        guid: header.guid_string(),
+        world_id: header.id,
+        width: header.width,
+        height: header.height,
+        size_class: header.size_class(),
+        game_mode: header.game_mode_name(),
+        evil: if header.crimson {
+            "Crimson"
+        } else {
+            "Corruption"
+        },
+        hardmode: header.hardmode,
+        spawn_x: header.spawn_x,
+        spawn_y: header.spawn_y,
+        surface_y: header.surface_y,
+        rock_y: header.rock_y,
+        times_saved: base_header.times_saved,
+        version: base_header.version,
+        generator_version: header.generator_version,
+        name: header.name,
+        seed: header.seed,
+    })
+}
+
+fn chest_info(wld_path: &Path, x: u16, y: u16) -> Result<ChestInfoReport, Box<dyn Error>> {
     let (file, base_header) = terraria_wld::open(wld_path, false)?;
-    let chests = terraria_wld::read_chests(&file, base_header.offsets.chests as u64)?;
+    let chests = read_chests_with_mods(wld_path, &file, &base_header)?;
     let chest_types = terraria_wld::read_chest_types(&file, &base_header)?;
     let ids = terraria_strings::item_ids();
-    for chest in &chests {
-        if chest.x == x && chest.y == y {
-            println!("{:?} Chest containing: ", chest_types[&(chest.x, chest.y)]);
-            for item in chest.items.iter() {
-                if item.stack > 0 {
-                    print!("{} ", item.stack);
-                    match ids.name_by_id(item.id as u16) {
-                        Some(name) => println!("{}", name),
-                        None => println!("Unknown({})", item.id),
-                    }
-                }
+    let chest = chests
+        .iter()
+        .find(|chest| chest.x == x && chest.y == y)
+        .map(|chest| {
+            let vanilla = chest
+                .items
+                .iter()
+                .filter(|item| item.stack > 0)
+                .map(|item| ReportItem {
+                    name: match ids.name_by_id(item.id as u16) {
+                        Some(name) => name.to_owned(),
+                        None => format!("Unknown({})", item.id),
+                    },
+                    stack: item.stack,
+                    prefix: prefix_names::name_by_id(item.prefix_id).map(str::to_owned),
+                });
+            let modded = chest.modded_items.iter().map(|item| ReportItem {
+                name: match modded_item_vanilla_name(item, ids) {
+                    Some(name) => name.to_owned(),
+                    None => item.full_name(),
+                },
+                stack: item.stack,
+                prefix: item
+                    .mod_prefix
+                    .clone()
+                    .or_else(|| prefix_names::name_by_id(item.prefix_id).map(str::to_owned)),
+            });
+            ChestContents {
+                type_: chest_type_name(chest_types.get(&(chest.x, chest.y)).copied()),
+                items: vanilla.chain(modded).collect(),
+            }
+        });
+    Ok(ChestInfoReport {
+        world: wld_path.display().to_string(),
+        x,
+        y,
+        chest,
+    })
+}
+
+fn export_chests(world_path: &Path, format: chest_export::Format) -> Result<(), Box<dyn Error>> {
+    let (file, base_header) = terraria_wld::open(world_path, false)?;
+    let chests = read_chests_with_mods(world_path, &file, &base_header)?;
+    let chest_types = terraria_wld::read_chest_types(&file, &base_header)?;
+    let exported = chest_export::export(&chests, &chest_types, terraria_strings::item_ids());
+    println!("{}", chest_export::to_string(&exported, format)?);
+    Ok(())
+}
+
+fn import_chests(world_path: &Path, chests_path: &Path) -> Result<(), Box<dyn Error>> {
+    let text = std::fs::read_to_string(chests_path)?;
+    let imported = chest_export::from_str(&text, chest_export::Format::from_path(chests_path))?;
+    let (file, mut base_header) = terraria_wld::open(world_path, true)?;
+    // Modded items are read too, so their slots don't get overwritten
+    let mut chests = read_chests_with_mods(world_path, &file, &base_header)?;
+    let n_updated = chest_export::import(&imported, &mut chests, terraria_strings::item_ids())?;
+    terraria_wld::write_chests(&file, &mut base_header, &chests)?;
+    println!("Updated {} chests.", n_updated);
+    Ok(())
+}
+
+/// Error message for an item name that doesn't exist, with suggestions for similar names
+fn unknown_item_msg(name: &str, ids: &terraria_strings::ItemIdMap) -> String {
+    unknown_name_msg("item", name, ids)
+}
+
+/// Like `unknown_item_msg`, for any `kind` of id, like "tile"
+fn unknown_name_msg(kind: &str, name: &str, ids: &terraria_strings::IdMap) -> String {
+    let suggestions = ids.suggestions(name, 3);
+    if suggestions.is_empty() {
+        format!("No matching id found for {} '{}'", kind, name)
+    } else {
+        format!(
+            "No matching id found for {} '{}'. Did you mean: {}?",
+            kind,
+            name,
+            suggestions.join(", ")
+        )
+    }
+}
+
+/// Below this score, matches are too different to list in search results
+const SEARCH_MIN_SCORE: f64 = 0.4;
+
+fn search_items(query: &str, limit: usize) {
+    let ids = terraria_strings::item_ids();
+    let matches = ids.search(query, SEARCH_MIN_SCORE);
+    if matches.is_empty() {
+        println!("No items found matching '{}'", query);
+        return;
+    }
+    for m in matches.iter().take(limit) {
+        println!("{:5} {}", m.id, m.name);
+    }
+}
+
+/// Format an item like "3 Torch" or "Warding Hermes Boots"
+fn format_item(item: &terraria_wld::Item, ids: &terraria_strings::ItemIdMap) -> String {
+    let mut out = String::new();
+    if item.stack > 1 {
+        out += &format!("{} ", item.stack);
+    }
+    if let Some(prefix) = prefix_names::name_by_id(item.prefix_id) {
+        out += &format!("{} ", prefix);
+    }
+    match ids.name_by_id(item.id as u16) {
+        Some(name) => out += name,
+        None => out += &format!("Unknown({})", item.id),
+    }
+    out
+}
+
+/// Format a tModLoader item like `format_item`, using its full name if it isn't a vanilla
+/// item
+fn format_modded_item(
+    item: &terraria_wld::tmod::ModdedItem,
+    ids: &terraria_strings::ItemIdMap,
+) -> String {
+    let mut out = String::new();
+    if item.stack > 1 {
+        out += &format!("{} ", item.stack);
+    }
+    match &item.mod_prefix {
+        Some(prefix) => out += &format!("{} ", prefix),
+        None => {
+            if let Some(prefix) = prefix_names::name_by_id(item.prefix_id) {
+                out += &format!("{} ", prefix);
             }
-            return Ok(());
         }
     }
-    println!("No chest at {}, {}", x, y);
+    match modded_item_vanilla_name(item, ids) {
+        Some(name) => out += name,
+        None => out += &item.full_name(),
+    }
+    out
+}
+
+/// The vanilla id of an item saved by tModLoader, if it is a vanilla item
+fn modded_item_vanilla_id(
+    item: &terraria_wld::tmod::ModdedItem,
+    ids: &terraria_strings::ItemIdMap,
+) -> Option<u16> {
+    if !item.is_vanilla() {
+        return None;
+    }
+    ids.id_by_internal_name(&item.name)
+}
+
+/// The vanilla name of an item saved by tModLoader, if it is a vanilla item
+fn modded_item_vanilla_name(
+    item: &terraria_wld::tmod::ModdedItem,
+    ids: &terraria_strings::ItemIdMap,
+) -> Option<&'static str> {
+    ids.name_by_id(modded_item_vanilla_id(item, ids)?)
+}
+
+/// Read the chests of a world, along with the modded items from its `.twld` file if it has one
+/// The chest type shown in reports, "Unknown" for chests on tiles whose types aren't read,
+/// like modded ones
+fn chest_type_name(chest_type: Option<terraria_wld::ChestType>) -> String {
+    chest_type.map_or_else(|| "Unknown".to_owned(), |type_| format!("{:?}", type_))
+}
+
+fn read_chests_with_mods(
+    world_path: &Path,
+    file: &File,
+    base_header: &terraria_wld::BaseHeader,
+) -> Result<Vec<terraria_wld::Chest>, Box<dyn Error>> {
+    let mut chests = terraria_wld::read_chests(file, base_header.offsets.chests as u64)?;
+    let twld_path = terraria_wld::tmod::twld_path(world_path);
+    if twld_path.exists() {
+        let mod_data = terraria_wld::tmod::read_twld(&twld_path)
+            .map_err(|e| format!("Failed to read {}: {}", twld_path.display(), e))?;
+        mod_data.merge_chests(&mut chests);
+    }
+    Ok(chests)
+}
+
+fn player_info(path: &Path) -> Result<(), Box<dyn Error>> {
+    let player = terraria_plr::read_player(path)?;
+    let ids = terraria_strings::item_ids();
+    println!("{} ({:?})", player.name, player.difficulty);
+    println!(
+        "Life: {}/{}, Mana: {}/{}",
+        player.life, player.max_life, player.mana, player.max_mana
+    );
+    let print_items = |title: &str, items: &[terraria_wld::Item]| {
+        if items.iter().all(|item| item.stack == 0) {
+            return;
+        }
+        println!("{}:", title);
+        for item in items.iter().filter(|item| item.stack != 0) {
+            println!("    {}", format_item(item, ids));
+        }
+    };
+    print_items("Armor", &player.armor[..3]);
+    print_items("Accessories", &player.armor[3..10]);
+    print_items("Vanity", &player.armor[10..]);
+    print_items("Equipment", &player.misc_equips);
+    let inventory: Vec<_> = player.inventory.iter().map(|slot| slot.item).collect();
+    print_items("Inventory", &inventory);
+    print_items("Piggy bank", &player.piggy_bank);
+    print_items("Safe", &player.safe);
+    print_items("Defender's forge", &player.defenders_forge);
+    print_items("Void vault", &player.void_vault);
+    println!("{} active buffs", player.buffs.len());
+    if !player.research.is_empty() {
+        println!("{} items researched", player.research.len());
+    }
     Ok(())
 }
 
@@ -179,80 +741,484 @@ fn is_inaccessible(x: u16, y: u16, header: &terraria_wld::Header) -> bool {
         || y > header.height - INACCESSIBLE_EDGE
 }
 
-fn itemhunt<T, Iter>(cfg_path: &Path, world_paths: Iter) -> Result<(), Box<dyn Error>>
-where
-    T: AsRef<Path>,
-    Iter: IntoIterator<Item = T>,
-{
-    let id_map = terraria_strings::item_ids();
-    let mut required_items = req_file::from_path::<u16>(cfg_path, &id_map)?;
-    let mut n_meet_reqs = 0;
-    for world_path in world_paths {
-        let world_path = world_path.as_ref();
-        eprintln!("{}:", world_path.display());
-        let (file, base_header) = terraria_wld::open(world_path, false)?;
-        let header = terraria_wld::read_header(&file, base_header.offsets.header as u64)?;
-        let chests = terraria_wld::read_chests(&file, base_header.offsets.chests as u64)?;
-        for chest in &chests[..] {
-            if is_inaccessible(chest.x, chest.y, &header) {
-                eprintln!(
-                    "Warning: Ignoring out-of-bounds chest at {}, {}",
-                    chest.x, chest.y
-                );
-                continue;
+/// Straight line distance between two tile positions
+fn tile_distance((x1, y1): (i32, i32), (x2, y2): (i32, i32)) -> f64 {
+    f64::from(x1 - x2).hypot(f64::from(y1 - y2))
+}
+
+/// Whether a chest is in a place the location constraints allow, not counting spacing
+fn fits_location(
+    chest: &terraria_wld::Chest,
+    location: &req_file::Location,
+    header: &terraria_wld::Header,
+    biome_map: Option<&biomes::BiomeMap>,
+) -> bool {
+    let pos = (i32::from(chest.x), i32::from(chest.y));
+    let spawn_distance = tile_distance(pos, (header.spawn_x, header.spawn_y));
+    let side = if pos.0 < header.spawn_x {
+        terraria_wld::XSide::West
+    } else {
+        terraria_wld::XSide::East
+    };
+    (location.layers.is_empty() || location.layers.contains(&header.layer(chest.y)))
+        && (location.biomes.is_empty()
+            || biome_map
+                .and_then(|map| map.biome_around(chest.x, chest.y))
+                .is_some_and(|biome| location.biomes.contains(&biome)))
+        && location.side.is_none_or(|s| s == side)
+        && location
+            .min_distance
+            .is_none_or(|min| spawn_distance >= f64::from(min))
+        && location
+            .max_distance
+            .is_none_or(|max| spawn_distance <= f64::from(max))
+}
+
+struct Summarized {
+    /// `None` if the world can't be cached
+    key: Option<String>,
+    summary: WorldSummary,
+    /// The summary didn't come from the cache
+    is_new: bool,
+}
+
+/// Summarize the chests of a world, or take the summary from the cache if the world
+/// didn't change since
+fn summarize_world(
+    world_path: &Path,
+    cache: Option<&SummaryCache>,
+) -> Result<Summarized, Box<dyn Error>> {
+    let (file, base_header) = terraria_wld::open(world_path, false)?;
+    let header = terraria_wld::read_header(&file, base_header.offsets.header as u64)?;
+    let key = summary_cache::cache_key(&header, &base_header, &file.metadata()?);
+    if let Some(summary) = cache.zip(key.as_deref()).and_then(|(c, key)| c.get(key)) {
+        return Ok(Summarized {
+            summary: summary.clone(),
+            key,
+            is_new: false,
+        });
+    }
+    let chests = read_chests_with_mods(world_path, &file, &base_header)?;
+    let mut summary = WorldSummary {
+        stacks: HashMap::new(),
+        inaccessible_chests: Vec::new(),
+    };
+    for chest in &chests[..] {
+        if is_inaccessible(chest.x, chest.y, &header) {
+            summary.inaccessible_chests.push((chest.x, chest.y));
+            continue;
+        }
+        for item in &chest.items[..] {
+            if item.stack != 0 {
+                *summary.stacks.entry(item.id).or_insert(0) += 1;
             }
-            for item in &chest.items[..] {
-                if item.stack != 0 {
-                    for req in &mut required_items {
-                        if item.id == i32::from(req.id) {
-                            req.tracker += 1;
-                        }
-                    }
-                }
+        }
+        // Only vanilla items can be required, so other modded items don't count
+        for item in &chest.modded_items {
+            if let Some(id) = modded_item_vanilla_id(item, terraria_strings::item_ids()) {
+                *summary.stacks.entry(i32::from(id)).or_insert(0) += 1;
             }
         }
-        let mut didnt_meet_reqs = false;
-        for req in &required_items {
-            if req.tracker < req.n_stacks {
-                didnt_meet_reqs = true;
-                let msg = format!(
-                    "{} - {}/{}",
-                    id_map.name_by_id(req.id).ok_or("Invalid id")?,
-                    req.tracker,
-                    req.n_stacks
-                );
-                println!("{}", Red.paint(msg));
+    }
+    Ok(Summarized {
+        key,
+        summary,
+        is_new: true,
+    })
+}
+
+/// Check the chests of a world against the requirements
+fn hunt_world(
+    world_path: &Path,
+    summary: &WorldSummary,
+    reqs: &[req_file::Requirement<()>],
+) -> ItemhuntReport {
+    let id_map = terraria_strings::item_ids();
+    for (x, y) in &summary.inaccessible_chests {
+        eprintln!(
+            "Warning: {}: Ignoring out-of-bounds chest at {}, {}",
+            world_path.display(),
+            x,
+            y
+        );
+    }
+    let mut missing = Vec::new();
+    let mut score = 0.0;
+    for req in reqs {
+        let found: u16 = req
+            .items
+            .iter()
+            .filter_map(|item| summary.stacks.get(&i32::from(item.id)))
+            .sum();
+        if found < req.n_stacks {
+            missing.push(MissingItem {
+                item: req.name(id_map),
+                section: req.section.clone(),
+                found,
+                required: req.n_stacks,
+            });
+            score += req.weight * f64::from(found) / f64::from(req.n_stacks);
+        } else {
+            score += req.weight;
+        }
+    }
+    ItemhuntReport {
+        world: world_path.display().to_string(),
+        meets_requirements: missing.is_empty(),
+        score,
+        max_score: reqs.iter().map(|req| req.weight).sum(),
+        requirements_met: reqs.len() - missing.len(),
+        requirements: reqs.len(),
+        rank: None,
+        missing,
+    }
+}
+
+/// Summarize all worlds, spread over as many threads as there are CPUs. The results are
+/// in the order of `world_paths`.
+fn summarize_worlds(
+    world_paths: &[PathBuf],
+    cache: Option<&SummaryCache>,
+) -> Vec<Result<Summarized, String>> {
+    let n_threads = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(world_paths.len());
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..world_paths.len()).map(|_| None).collect::<Vec<_>>());
+    std::thread::scope(|scope| {
+        for _ in 0..n_threads {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let path = match world_paths.get(i) {
+                    Some(path) => path,
+                    None => break,
+                };
+                // Errors aren't Send, so they're passed on as strings
+                let result =
+                    summarize_world(path, cache).map_err(|e| format!("{}: {}", path.display(), e));
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(Option::unwrap)
+        .collect()
+}
+
+/// Check worlds against the requirements. With `top`, only the best scoring worlds are
+/// printed, best first. With `cache_path`, chest summaries are kept in that file, so
+/// worlds that didn't change don't need to be read again next time. Worlds that
+/// can't be read are reported and skipped.
+fn itemhunt(
+    cfg_path: &Path,
+    world_paths: &[PathBuf],
+    top: Option<usize>,
+    cache_path: Option<&Path>,
+    printer: &mut Printer,
+) -> Result<(), Box<dyn Error>> {
+    let reqs = req_file::from_path::<()>(cfg_path, terraria_strings::item_ids())?;
+    let mut cache = cache_path.map(SummaryCache::load).transpose()?;
+    let summaries = summarize_worlds(world_paths, cache.as_ref());
+    let mut reports = Vec::new();
+    let mut n_meet_reqs = 0;
+    for (world_path, result) in world_paths.iter().zip(summaries) {
+        // A bad world is skipped, so it doesn't throw away the summaries of the others
+        let summarized = match result {
+            Ok(summarized) => summarized,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                continue;
             }
+        };
+        let report = hunt_world(world_path, &summarized.summary, &reqs);
+        if let (Some(cache), Some(key), true) = (&mut cache, summarized.key, summarized.is_new) {
+            cache.insert(key, summarized.summary);
         }
-        if !didnt_meet_reqs {
-            println!("{}", Green.paint("This world meets all requirements."));
+        if report.meets_requirements {
             n_meet_reqs += 1;
         }
-        // Reset times_found values
-        for req in &mut required_items {
-            req.tracker = 0;
+        if top.is_some() {
+            reports.push(report);
+        } else {
+            printer.print(&report)?;
+        }
+    }
+    if let (Some(cache), Some(path)) = (&cache, cache_path) {
+        cache.save(path)?;
+    }
+    if let Some(top) = top {
+        reports.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then(b.requirements_met.cmp(&a.requirements_met))
+                .then_with(|| a.world.cmp(&b.world))
+        });
+        reports.truncate(top);
+        for (i, report) in reports.iter_mut().enumerate() {
+            report.rank = Some(i + 1);
+            printer.print(&*report)?;
         }
     }
-    println!("{} worlds in total meet the requirements.", n_meet_reqs);
+    if printer.format() == output::Format::Text {
+        if !reports.is_empty() {
+            print_leaderboard(&reports);
+        }
+        println!("{} worlds in total meet the requirements.", n_meet_reqs);
+    }
     Ok(())
 }
 
-fn find_item(world_path: &Path, name: &str) -> Result<(), Box<dyn Error>> {
+fn print_leaderboard(reports: &[ItemhuntReport]) {
+    println!();
+    println!(
+        "{:>4}  {:>14}  {:>7}  {:>9}  world",
+        "rank", "score", "percent", "met"
+    );
+    for report in reports {
+        let percent = if report.max_score > 0.0 {
+            report.score / report.max_score * 100.0
+        } else {
+            100.0
+        };
+        println!(
+            "{:>4}  {:>14}  {:>6.1}%  {:>9}  {}",
+            report.rank.unwrap_or_default(),
+            format!("{:.2}/{:.2}", report.score, report.max_score),
+            percent,
+            format!("{}/{}", report.requirements_met, report.requirements),
+            report.world
+        );
+    }
+}
+
+fn find_item(world_path: &Path, name: &str) -> Result<FindReport, Box<dyn Error>> {
     let ids = terraria_strings::item_ids();
     let id = ids
-        .id_by_name(name)
-        .ok_or_else(|| format!("No matching id found for item '{}'", name))?;
+        .id_by_any_name(name)
+        .ok_or_else(|| unknown_item_msg(name, ids))?;
     let (file, base_header) = terraria_wld::open(world_path, false)?;
     let header = terraria_wld::read_header(&file, base_header.offsets.header as u64)?;
-    let chests = terraria_wld::read_chests(&file, base_header.offsets.chests as u64)?;
+    let chests = read_chests_with_mods(world_path, &file, &base_header)?;
+    let mut locations = Vec::new();
     for chest in &chests[..] {
-        for item in &chest.items[..] {
-            if item.stack != 0 && item.id == i32::from(id) {
-                let pos = header.tile_to_gps_pos(chest.x, chest.y);
-                println!("Found in chest at {}", pos);
+        let vanilla = chest
+            .items
+            .iter()
+            .filter(|item| item.stack != 0 && item.id == i32::from(id))
+            .count();
+        let modded = chest
+            .modded_items
+            .iter()
+            .filter(|item| modded_item_vanilla_id(item, ids) == Some(id))
+            .count();
+        for _ in 0..vanilla + modded {
+            locations.push(ChestLocation {
+                x: chest.x,
+                y: chest.y,
+                gps: header.tile_to_gps_pos(chest.x, chest.y).to_string(),
+            });
+        }
+    }
+    Ok(FindReport {
+        world: world_path.display().to_string(),
+        item: ids.name_by_id(id).unwrap_or(name).to_owned(),
+        chests: locations,
+    })
+}
+
+/// Which tiles or objects `find-tile` should match, besides the tile id
+struct TileFilter {
+    frame_x: Option<u16>,
+    frame_y: Option<u16>,
+    style: Option<u16>,
+}
+
+impl TileFilter {
+    fn matches_frame(&self, frame: terraria_wld::TileFrameOffset) -> bool {
+        self.frame_x.is_none_or(|fx| fx == frame.x) && self.frame_y.is_none_or(|fy| fy == frame.y)
+    }
+}
+
+fn find_tile(
+    world_path: &Path,
+    id: u16,
+    filter: &TileFilter,
+) -> Result<FindTileReport, Box<dyn Error>> {
+    let (file, base_header) = terraria_wld::open(world_path, false)?;
+    let header = terraria_wld::read_header(&file, base_header.offsets.header as u64)?;
+    let location = |x, y, width, height, style| TileLocation {
+        x,
+        y,
+        width,
+        height,
+        style,
+        gps: header.tile_to_gps_pos(x, y).to_string(),
+    };
+    let objects = if terraria_wld::objects::object_shape(id).is_some() {
+        terraria_wld::objects::read_objects(&file, &base_header, |tile_id| tile_id == id)?
+            .into_iter()
+            .filter(|object| {
+                let frame = terraria_wld::TileFrameOffset {
+                    x: object.frame_x,
+                    y: object.frame_y,
+                };
+                filter.matches_frame(frame) && filter.style.is_none_or(|s| s == object.style)
+            })
+            .map(|object| {
+                location(
+                    object.x,
+                    object.y,
+                    object.width,
+                    object.height,
+                    Some(object.style),
+                )
+            })
+            .collect()
+    } else {
+        let mut positions = HashSet::new();
+        terraria_wld::read_tiles(&file, &base_header, |tile, x, y| {
+            if tile.front == Some(id) && tile.frame.is_none_or(|f| filter.matches_frame(f)) {
+                positions.insert((x, y));
+            }
+        })?;
+        group_touching_tiles(positions)
+            .into_iter()
+            .map(|(x, y, width, height)| location(x, y, width, height, None))
+            .collect()
+    };
+    Ok(FindTileReport {
+        world: world_path.display().to_string(),
+        tile: match terraria_strings::tile_ids().name_by_id(id) {
+            Some(name) => name.to_owned(),
+            None => id.to_string(),
+        },
+        objects,
+    })
+}
+
+fn find_structures(world_path: &Path) -> Result<StructuresReport, Box<dyn Error>> {
+    use terraria_wld::structures::DungeonColor;
+    let (file, base_header) = terraria_wld::open(world_path, false)?;
+    let header = terraria_wld::read_header(&file, base_header.offsets.header as u64)?;
+    let found = terraria_wld::structures::find_structures(&file, &base_header)?;
+    let gps = |x: i32, y: i32| {
+        header
+            .tile_to_gps_pos(
+                x.clamp(0, u16::MAX.into()) as u16,
+                y.clamp(0, u16::MAX.into()) as u16,
+            )
+            .to_string()
+    };
+    Ok(StructuresReport {
+        world: world_path.display().to_string(),
+        dungeon: found.dungeon.map(|dungeon| DungeonReport {
+            side: match dungeon.side {
+                terraria_wld::XSide::West => "west",
+                terraria_wld::XSide::East => "east",
+            },
+            color: match dungeon.color {
+                DungeonColor::Blue => "blue",
+                DungeonColor::Green => "green",
+                DungeonColor::Pink => "pink",
+            },
+            entrance_x: dungeon.entrance_x,
+            entrance_y: dungeon.entrance_y,
+            entrance_gps: gps(dungeon.entrance_x, dungeon.entrance_y),
+        }),
+        structures: found
+            .structures
+            .iter()
+            .map(|s| StructureLocation {
+                kind: s.kind.name(),
+                x: s.x,
+                y: s.y,
+                width: s.width,
+                height: s.height,
+                tiles: s.tiles,
+                gps: gps(i32::from(s.x), i32::from(s.y)),
+            })
+            .collect(),
+    })
+}
+
+/// Group touching tiles together, for tiles that aren't known objects, like ore veins.
+///
+/// Returns the (x, y, width, height) bounding box of each group.
+fn group_touching_tiles(mut positions: HashSet<(u16, u16)>) -> Vec<(u16, u16, u16, u16)> {
+    let mut groups = Vec::new();
+    let mut sorted: Vec<_> = positions.iter().copied().collect();
+    sorted.sort_unstable_by_key(|&(x, y)| (y, x));
+    for start in sorted {
+        if !positions.remove(&start) {
+            continue;
+        }
+        let (mut left, mut top, mut right, mut bottom) = (start.0, start.1, start.0, start.1);
+        let mut stack = vec![start];
+        while let Some((x, y)) = stack.pop() {
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x);
+            bottom = bottom.max(y);
+            let neighbors = [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ];
+            for pos in neighbors {
+                if positions.remove(&pos) {
+                    stack.push(pos);
+                }
             }
         }
+        groups.push((left, top, right - left + 1, bottom - top + 1));
+    }
+    groups
+}
+
+fn undiscovered_chests(world_path: &Path, player_or_map_path: &Path) -> Result<(), Box<dyn Error>> {
+    let (file, base_header) = terraria_wld::open(world_path, false)?;
+    let header = terraria_wld::read_header(&file, base_header.offsets.header as u64)?;
+    let map_path = if player_or_map_path
+        .extension()
+        .is_some_and(|ext| ext == "plr")
+    {
+        terraria_map::player_map_path(player_or_map_path, &header)
+    } else {
+        player_or_map_path.to_owned()
+    };
+    let map = terraria_map::read_map(&map_path)
+        .map_err(|e| format!("Failed to read map {}: {}", map_path.display(), e))?;
+    if !map.matches(&header) {
+        return Err(format!("Map {} is not of world {}", map_path.display(), header.name).into());
+    }
+    let chests = read_chests_with_mods(world_path, &file, &base_header)?;
+    let ids = terraria_strings::item_ids();
+    let mut n_undiscovered = 0;
+    for chest in &chests {
+        // Chests are 2x2, consider them discovered if any part is
+        let discovered = (0..2).any(|dx| (0..2).any(|dy| map.explored(chest.x + dx, chest.y + dy)));
+        if discovered {
+            continue;
+        }
+        n_undiscovered += 1;
+        println!("Chest at {}", header.tile_to_gps_pos(chest.x, chest.y));
+        for item in chest.items.iter().filter(|item| item.stack != 0) {
+            println!("    {}", format_item(item, ids));
+        }
+        for item in &chest.modded_items {
+            println!("    {}", format_modded_item(item, ids));
+        }
     }
+    println!(
+        "{} of {} chests undiscovered, {:.1}% of the world explored",
+        n_undiscovered,
+        chests.len(),
+        map.explored_fraction() * 100.0
+    );
     Ok(())
 }
 
@@ -279,24 +1245,119 @@ fn fix_npcs(world_path: &Path) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Put a stack of the item in the first free slot of the chest.
+///
+/// Returns the stack size, or `None` if the chest is full.
 fn place_in_chest(
     chest: &mut terraria_wld::Chest,
     id: i32,
     prefix: u8,
     min_stack: u16,
     max_stack: u16,
-    rng: &mut ThreadRng,
-) {
-    for item in chest.items.iter_mut() {
-        if item.stack == 0 {
-            item.stack = if min_stack == 1 && max_stack == 1 {
-                1
-            } else {
-                rng.gen_range(min_stack..max_stack)
-            };
-            item.id = id;
-            item.prefix_id = prefix;
-            return;
+    rng: &mut impl Rng,
+) -> Option<u16> {
+    let slot = (0..chest.items.len()).find(|&slot| chest.slot_is_free(slot))?;
+    let item = &mut chest.items[slot];
+    item.stack = rng.gen_range(min_stack..=max_stack);
+    item.id = id;
+    item.prefix_id = prefix;
+    Some(item.stack)
+}
+
+/// A stack placed by [`allocate`]
+struct Allocated {
+    /// Index of the chest
+    chest: usize,
+    item: req_file::ReqItem,
+    stack: u16,
+    /// The chest in turn was full or too close to other placed stacks, so it went into a
+    /// later one
+    spilled_over: bool,
+}
+
+/// Place the stacks of a requirement in the `candidates` chests. The chests are taken in
+/// turn, skipping the ones that are full or too close to the stacks in `placed_at`, which
+/// holds the positions of everything placed so far, for all requirements.
+///
+/// Returns the placed stacks, and how many stacks didn't fit anywhere.
+fn allocate<T: Default>(
+    req: &req_file::Requirement<T>,
+    chests: &mut [terraria_wld::Chest],
+    free_slots: &mut [usize],
+    placed_at: &mut Vec<(i32, i32)>,
+    candidates: &[usize],
+    rng: &mut impl Rng,
+) -> (Vec<Allocated>, u16) {
+    let positions: Vec<(i32, i32)> = candidates
+        .iter()
+        .map(|&index| (i32::from(chests[index].x), i32::from(chests[index].y)))
+        .collect();
+    let far_enough = |i: usize, placed_at: &[(i32, i32)]| {
+        req.location.spacing.is_none_or(|spacing| {
+            placed_at
+                .iter()
+                .all(|&other| tile_distance(positions[i], other) >= f64::from(spacing))
+        })
+    };
+    let mut allocated = Vec::new();
+    let mut missing = 0;
+    let mut next = 0;
+    for tried_stacks in 0..req.n_stacks {
+        let found = (0..candidates.len())
+            .map(|skipped| (skipped, (next + skipped) % candidates.len()))
+            .find(|&(_, i)| free_slots[candidates[i]] > 0 && far_enough(i, placed_at));
+        let (skipped, i) = match found {
+            Some(found) => found,
+            None => return (allocated, missing + req.n_stacks - tried_stacks),
+        };
+        next = i + 1;
+        let index = candidates[i];
+        free_slots[index] -= 1;
+        // Every stack of a group can be a different one of its items
+        let item = *req.items.choose(rng).unwrap();
+        let placed = place_in_chest(
+            &mut chests[index],
+            i32::from(item.id),
+            item.prefix_id,
+            req.min_per_stack,
+            req.max_per_stack,
+            rng,
+        );
+        match placed {
+            Some(stack) => {
+                placed_at.push(positions[i]);
+                allocated.push(Allocated {
+                    chest: index,
+                    item,
+                    stack,
+                    spilled_over: skipped > 0,
+                });
+            }
+            None => {
+                // The free slots were miscounted, so don't try the chest again
+                free_slots[index] = 0;
+                missing += 1;
+            }
+        }
+    }
+    (allocated, missing)
+}
+
+/// What bless-chests does when an item doesn't fit anywhere
+#[derive(Clone, Copy)]
+enum OnFull {
+    Fail,
+    Skip,
+}
+
+impl std::str::FromStr for OnFull {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_lowercase()[..] {
+            "fail" => Ok(OnFull::Fail),
+            "skip" => Ok(OnFull::Skip),
+            _ => Err(format!("Unknown value '{}', expected fail or skip", s)),
         }
     }
 }
@@ -305,35 +1366,44 @@ fn validate_req_for_bless<T: Default>(reqs: &[req_file::Requirement<T>]) -> Resu
     let ids = terraria_strings::item_ids();
     for req in reqs {
         if req.only_in.is_empty() {
-            let name = ids.name_by_id(req.id).unwrap();
             return Err(format!(
                 "You need to specify at least one chest type for {}",
-                name
+                req.name(ids)
             ));
         }
     }
     Ok(())
 }
 
-fn bless_chests(cfg_path: &Path, world_path: &Path) -> Result<(), Box<dyn Error>> {
+/// Place the required items in the world's chests, or with `dry_run`, only work out
+/// where they would go
+fn bless_chests(
+    cfg_path: &Path,
+    world_path: &Path,
+    seed: u64,
+    dry_run: bool,
+    on_full: OnFull,
+) -> Result<BlessReport, Box<dyn Error>> {
     let item_ids = terraria_strings::item_ids();
+    #[derive(Default)]
     struct Tracker {
-        acceptable_chest_indexes: Box<dyn Iterator<Item = usize>>,
+        acceptable_chest_indexes: Vec<usize>,
     }
-    impl Default for Tracker {
-        fn default() -> Self {
-            Self {
-                acceptable_chest_indexes: Box::new(::std::iter::empty()),
-            }
-        }
-    }
-    let mut reqs = req_file::from_path::<Tracker>(cfg_path, &item_ids)?;
+    let mut reqs = req_file::from_path::<Tracker>(cfg_path, item_ids)?;
     validate_req_for_bless(&reqs)?;
-    let (file, mut base_header) = terraria_wld::open(world_path, true)?;
-    let mut chests = terraria_wld::read_chests(&file, base_header.offsets.chests as u64)?;
+    let (file, mut base_header) = terraria_wld::open(world_path, !dry_run)?;
+    // Modded items are read too, so their slots don't get used
+    let mut chests = read_chests_with_mods(world_path, &file, &base_header)?;
     let header = terraria_wld::read_header(&file, base_header.offsets.header as u64)?;
     let chest_types = terraria_wld::read_chest_types(&file, &base_header)?;
-    let mut rng = thread_rng();
+    // Going through all tiles takes a while, so only when it's needed
+    let biome_map = if reqs.iter().any(|req| !req.location.biomes.is_empty()) {
+        Some(biomes::BiomeMap::read(&file, &base_header, &header)?)
+    } else {
+        None
+    };
+    // Not StdRng, whose algorithm may change with rand versions and break old seeds
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
     let chest_indexes = 0..chests.len();
     for req in &mut reqs {
         // Decrease stack count for every item that already exists in the world
@@ -342,206 +1412,434 @@ fn bless_chests(cfg_path: &Path, world_path: &Path) -> Result<(), Box<dyn Error>
                 continue;
             }
             for item in &chest.items[..] {
-                if item.stack != 0 && item.id == i32::from(req.id) && req.n_stacks > 0 {
+                if item.stack != 0 && req.matches(item.id) && req.n_stacks > 0 {
                     req.n_stacks -= 1;
                 }
             }
         }
-        // Set up chest indexes to place the item in. Might be only specific chest types.
+        // Set up chest indexes to place the item in. Might be only specific chest types
+        // and places in the world.
         if req.n_stacks > 0 {
             let mut matching_indexes: Vec<usize> = chest_indexes
                 .clone()
                 .filter(|&idx| {
                     let chest = &chests[idx];
-                    let type_ = chest_types[&(chest.x, chest.y)];
-                    req.only_in.contains(&type_) && !is_inaccessible(chest.x, chest.y, &header)
+                    chest_types
+                        .get(&(chest.x, chest.y))
+                        .is_some_and(|type_| req.only_in.contains(type_))
+                        && !is_inaccessible(chest.x, chest.y, &header)
+                        && fits_location(chest, &req.location, &header, biome_map.as_ref())
                 })
                 .collect();
             matching_indexes.shuffle(&mut rng);
-            req.tracker.acceptable_chest_indexes = Box::new(matching_indexes.into_iter().cycle())
+            req.tracker.acceptable_chest_indexes = matching_indexes;
         }
     }
-    for mut req in reqs {
-        for _ in 0..req.n_stacks {
-            let index = match req.tracker.acceptable_chest_indexes.next() {
-                Some(idx) => idx,
-                None => {
-                    let name = item_ids.name_by_id(req.id).unwrap();
-                    return Err(format!("No chest available for {}", name).into());
+    let mut free_slots: Vec<usize> = chests
+        .iter()
+        .map(|chest| {
+            (0..chest.items.len())
+                .filter(|&slot| chest.slot_is_free(slot))
+                .count()
+        })
+        .collect();
+    let mut placements = Vec::new();
+    let mut unplaced = Vec::new();
+    let mut placed_at = Vec::new();
+    for req in reqs {
+        let name = req.name(item_ids);
+        let candidates = &req.tracker.acceptable_chest_indexes;
+        let (allocated, missing) = allocate(
+            &req,
+            &mut chests,
+            &mut free_slots,
+            &mut placed_at,
+            candidates,
+            &mut rng,
+        );
+        if missing > 0 {
+            match on_full {
+                OnFull::Fail if candidates.is_empty() => {
+                    return Err(format!("No chest available for {}", name).into())
                 }
-            };
-            let chest = &mut chests[index];
-            place_in_chest(
-                chest,
-                i32::from(req.id),
-                req.prefix_id,
-                req.min_per_stack,
-                req.max_per_stack,
-                &mut rng,
-            );
+                OnFull::Fail => {
+                    return Err(format!(
+                        "All {} chests for {} are full{}, {} of {} stacks couldn't be placed. \
+                         Nothing was changed.",
+                        candidates.len(),
+                        name,
+                        if req.location.spacing.is_some() {
+                            " or too close to other placed stacks"
+                        } else {
+                            ""
+                        },
+                        missing,
+                        req.n_stacks
+                    )
+                    .into())
+                }
+                OnFull::Skip => unplaced.push(Unplaced {
+                    item: name.clone(),
+                    stacks: missing,
+                    reason: if candidates.is_empty() {
+                        "no matching chests"
+                    } else if req.location.spacing.is_some() {
+                        "all chests full or too close to other placed stacks"
+                    } else {
+                        "all chests full"
+                    },
+                }),
+            }
+        }
+        for placed in allocated {
+            let chest = &chests[placed.chest];
+            placements.push(Placement {
+                item: item_ids
+                    .name_by_id(placed.item.id)
+                    .unwrap_or_default()
+                    .to_owned(),
+                stack: placed.stack,
+                prefix: prefix_names::name_by_id(placed.item.prefix_id).map(str::to_owned),
+                x: chest.x,
+                y: chest.y,
+                chest_type: chest_type_name(chest_types.get(&(chest.x, chest.y)).copied()),
+                gps: header.tile_to_gps_pos(chest.x, chest.y).to_string(),
+                spilled_over: placed.spilled_over,
+            });
         }
     }
-    terraria_wld::write_chests(&file, &mut base_header, &chests)?;
-    Ok(())
+    if !dry_run {
+        terraria_wld::write_chests(&file, &mut base_header, &chests)?;
+    }
+    Ok(BlessReport {
+        world: world_path.display().to_string(),
+        seed,
+        dry_run,
+        placements,
+        unplaced,
+    })
 }
 
-fn analyze_chests(world_path: &Path) -> Result<(), Box<dyn Error>> {
+fn analyze_chests(world_path: &Path) -> Result<AnalyzeChestsReport, Box<dyn Error>> {
     let (file, base_header) = terraria_wld::open(world_path, false)?;
-    let chests = terraria_wld::read_chests(&file, base_header.offsets.chests as u64)?;
-    #[derive(Debug)]
-    struct ItemStat {
-        stack_count: u32,
-        total_count: u32,
-    }
-    let mut item_stats: HashMap<i32, ItemStat> = HashMap::new();
+    let chests = read_chests_with_mods(world_path, &file, &base_header)?;
+    let ids = terraria_strings::item_ids();
+    // Keyed by name, so modded items can be counted alongside vanilla ones
+    let mut item_stats: HashMap<String, ItemStat> = HashMap::new();
     let mut chests_containing_something = 0;
     for chest in &chests {
+        let vanilla = chest
+            .items
+            .iter()
+            .filter(|item| item.stack != 0)
+            .map(|item| {
+                let name = match ids.name_by_id(item.id as u16) {
+                    Some(name) => name.to_owned(),
+                    None => format!("unknown({:4})", item.id),
+                };
+                (name, item.stack)
+            });
+        let modded = chest.modded_items.iter().map(|item| {
+            let name = match modded_item_vanilla_name(item, ids) {
+                Some(name) => name.to_owned(),
+                None => item.full_name(),
+            };
+            (name, item.stack)
+        });
         let mut contains_something = false;
-        for item in chest.items.iter() {
-            if item.stack != 0 {
-                contains_something = true;
-                match item_stats.get_mut(&item.id) {
-                    Some(ref mut stat) => {
-                        stat.stack_count += 1;
-                        stat.total_count += u32::from(item.stack);
-                    }
-                    None => {
-                        item_stats.insert(
-                            item.id,
-                            ItemStat {
-                                stack_count: 1,
-                                total_count: u32::from(item.stack),
-                            },
-                        );
-                    }
-                }
-            }
+        for (name, stack) in vanilla.chain(modded) {
+            contains_something = true;
+            let stat = item_stats.entry(name.clone()).or_insert(ItemStat {
+                name,
+                stacks: 0,
+                total: 0,
+            });
+            stat.stacks += 1;
+            stat.total += u32::from(stack);
         }
         if contains_something {
             chests_containing_something += 1;
         }
     }
-    let mut vec = item_stats.into_iter().collect::<Vec<_>>();
-    vec.sort_by(|&(_, ref v1), &(_, ref v2)| v1.stack_count.cmp(&v2.stack_count).reverse());
-    let ids = terraria_strings::item_ids();
-    println!("{:30}stack total", "name");
-    for (k, v) in vec {
-        match ids.name_by_id(k as u16) {
-            Some(name) => print!("{:30}", name),
-            None => print!("unknown({:4})                 ", k),
-        }
-        println!("{:<5} {}", v.stack_count, v.total_count);
+    let mut items = item_stats.into_values().collect::<Vec<_>>();
+    items.sort_by(|s1, s2| s1.stacks.cmp(&s2.stacks).reverse());
+    Ok(AnalyzeChestsReport {
+        world: world_path.display().to_string(),
+        chests_containing_something,
+        items,
+    })
+}
+
+#[cfg(test)]
+fn test_requirement(n_stacks: u16, stack_range: (u16, u16)) -> req_file::Requirement<()> {
+    req_file::Requirement {
+        items: vec![req_file::ReqItem {
+            id: terraria_strings::ItemId::TORCH.0,
+            prefix_id: 0,
+        }],
+        n_stacks,
+        min_per_stack: stack_range.0,
+        max_per_stack: stack_range.1,
+        only_in: vec![terraria_wld::ChestType::Gold],
+        location: Default::default(),
+        tracker: (),
+        weight: 1.0,
+        section: None,
     }
-    println!(
-        "{} total chests that contain something",
-        chests_containing_something
+}
+
+/// A small world's header, with the surface at 300, the caverns at 400 and spawn in the
+/// middle at the surface
+#[cfg(test)]
+fn test_header(width: u16, height: u16) -> terraria_wld::Header {
+    terraria_wld::Header {
+        id: 0,
+        bounds: terraria_wld::Rect {
+            left: 0,
+            right: i32::from(width) * 16,
+            top: 0,
+            bottom: i32::from(height) * 16,
+        },
+        name: "test".to_owned(),
+        height,
+        width,
+        surface_y: 300.0,
+        seed: String::new(),
+        spawn_x: i32::from(width) / 2,
+        spawn_y: 300,
+        generator_version: 0,
+        guid: [0; 16],
+        game_mode: 0,
+        rock_y: 400.0,
+        dungeon_x: 0,
+        dungeon_y: 0,
+        crimson: false,
+        hardmode: false,
+    }
+}
+
+#[cfg(test)]
+fn test_chest(x: u16, used_slots: usize) -> terraria_wld::Chest {
+    let mut chest = terraria_wld::Chest {
+        x,
+        y: 100,
+        name: String::new(),
+        items: [terraria_wld::Item::default(); 40],
+        modded_items: Vec::new(),
+    };
+    for item in &mut chest.items[..used_slots] {
+        item.id = 1;
+        item.stack = 1;
+    }
+    chest
+}
+
+#[test]
+fn test_allocate_full_chests() {
+    let mut chests = vec![test_chest(0, 40), test_chest(10, 39)];
+    let mut free_slots = vec![0, 1];
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let req = test_requirement(3, (1, 1));
+    let (allocated, missing) = allocate(
+        &req,
+        &mut chests,
+        &mut free_slots,
+        &mut Vec::new(),
+        &[0, 1],
+        &mut rng,
     );
-    Ok(())
+    assert_eq!(missing, 2);
+    assert_eq!(allocated.len(), 1);
+    assert_eq!((allocated[0].chest, allocated[0].spilled_over), (1, true));
+    assert_eq!(
+        chests[1].items[39].id,
+        i32::from(terraria_strings::ItemId::TORCH)
+    );
+    assert_eq!(free_slots, [0, 0]);
+    let (allocated, missing) = allocate(
+        &req,
+        &mut chests,
+        &mut free_slots,
+        &mut Vec::new(),
+        &[],
+        &mut rng,
+    );
+    assert!(allocated.is_empty());
+    assert_eq!(missing, 3);
 }
 
-fn corruption_percent(path: &Path) -> Result<(), Box<dyn Error>> {
-    let (world_file, base_header) = terraria_wld::open(path, false)?;
-    let mut total = 0;
-    let mut corrupt = 0;
-    let mut crimson = 0;
-    terraria_wld::read_tiles(&world_file, &base_header, |tile, _, _| {
-        total += 1;
-        match tile.front {
-            Some(23 | 25 | 163 | 112) => corrupt += 1,
-            Some(199 | 200 | 203 | 234) => crimson += 1,
-            _ => {}
-        }
-    })?;
-    println!(
-        "Total: {}, Corrupt: {}, {:.2}%, Crimson: {}, {:.2}%",
-        total,
-        corrupt,
-        f64::from(corrupt) / f64::from(total) * 100.0,
-        crimson,
-        f64::from(crimson) / f64::from(total) * 100.0,
+#[test]
+fn test_allocate_spacing() {
+    let mut chests = vec![test_chest(0, 0), test_chest(10, 0), test_chest(100, 0)];
+    let mut free_slots = vec![40; 3];
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let mut req = test_requirement(3, (1, 1));
+    req.location.spacing = Some(50);
+    let (allocated, missing) = allocate(
+        &req,
+        &mut chests,
+        &mut free_slots,
+        &mut Vec::new(),
+        &[0, 1, 2],
+        &mut rng,
     );
-    Ok(())
+    let placed: Vec<(usize, bool)> = allocated
+        .iter()
+        .map(|a| (a.chest, a.spilled_over))
+        .collect();
+    // The second chest is too close to the first
+    assert_eq!(placed, [(0, false), (2, true)]);
+    assert_eq!(missing, 1);
 }
 
-fn count_ores(path: &Path) -> Result<(), Box<dyn Error>> {
-    let (world_file, base_header) = terraria_wld::open(path, false)?;
-    let mut copper = 0;
-    let mut tin = 0;
-    let mut iron = 0;
-    let mut lead = 0;
-    let mut silver = 0;
-    let mut tungsten = 0;
-    let mut gold = 0;
-    let mut platinum = 0;
-    let mut sapphire = 0;
-    let mut ruby = 0;
-    let mut emerald = 0;
-    let mut topaz = 0;
-    let mut amethyst = 0;
-    let mut diamond = 0;
-    let mut amber = 0;
-    terraria_wld::read_tiles(&world_file, &base_header, |tile, _, _| match tile.front {
-        Some(7) => copper += 1,
-        Some(166) => tin += 1,
-        Some(6) => iron += 1,
-        Some(167) => lead += 1,
-        Some(9) => silver += 1,
-        Some(168) => tungsten += 1,
-        Some(8) => gold += 1,
-        Some(169) => platinum += 1,
-        Some(63) => sapphire += 1,
-        Some(64) => ruby += 1,
-        Some(65) => emerald += 1,
-        Some(66) => topaz += 1,
-        Some(67) => amethyst += 1,
-        Some(68) => diamond += 1,
-        Some(566) => amber += 1,
-        Some(178) => {
-            let tfo = tile.frame.unwrap();
-            match tfo.x / 18 {
-                0 => amethyst += 1,
-                1 => topaz += 1,
-                2 => sapphire += 1,
-                3 => emerald += 1,
-                4 => ruby += 1,
-                5 => diamond += 1,
-                6 => amber += 1,
-                _ => panic!("invalid/unknown gem tile frame x"),
-            }
-        }
-        _ => {}
-    })?;
-    if copper > 0 {
-        println!("copper: {}", copper);
-    }
-    if tin > 0 {
-        println!("tin: {}", tin);
-    }
-    if iron > 0 {
-        println!("iron: {}", iron);
-    }
-    if lead > 0 {
-        println!("lead: {}", lead);
-    }
-    if silver > 0 {
-        println!("silver: {}", silver);
-    }
-    if tungsten > 0 {
-        println!("tungsten: {}", tungsten);
-    }
-    if gold > 0 {
-        println!("gold: {}", gold);
-    }
-    if platinum > 0 {
-        println!("platinum: {}", platinum);
-    }
-    println!("=============");
-    println!("ametyhst: {}", amethyst);
-    println!("topaz: {}", topaz);
-    println!("sapphire: {}", sapphire);
-    println!("emerald: {}", emerald);
-    println!("ruby: {}", ruby);
-    println!("diamond: {}", diamond);
-    println!("amber: {}", amber);
-    Ok(())
+#[test]
+fn test_allocate_stack_range() {
+    let mut chests = vec![test_chest(0, 0)];
+    let mut free_slots = vec![40];
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let req = test_requirement(40, (1, 3));
+    let (allocated, missing) = allocate(
+        &req,
+        &mut chests,
+        &mut free_slots,
+        &mut Vec::new(),
+        &[0],
+        &mut rng,
+    );
+    assert_eq!(missing, 0);
+    let stacks: HashSet<u16> = allocated.iter().map(|a| a.stack).collect();
+    assert_eq!(stacks, HashSet::from([1, 2, 3]));
+}
+
+#[test]
+fn test_allocate_same_seed() {
+    let chests: Vec<_> = (0..5).map(|i| test_chest(i * 10, 38)).collect();
+    let mut req = test_requirement(8, (1, 99));
+    req.items.push(req_file::ReqItem {
+        id: terraria_strings::ItemId::ROPE.0,
+        prefix_id: 0,
+    });
+    let run = |seed| {
+        let mut chests = chests.clone();
+        let mut free_slots = vec![2; 5];
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let (allocated, _) = allocate(
+            &req,
+            &mut chests,
+            &mut free_slots,
+            &mut Vec::new(),
+            &[4, 0, 2, 1, 3],
+            &mut rng,
+        );
+        allocated
+            .iter()
+            .map(|a| (a.chest, a.item.id, a.stack, a.spilled_over))
+            .collect::<Vec<_>>()
+    };
+    // Pinned, so a change of the random algorithm that would break old seeds is noticed
+    assert_eq!(
+        run(42),
+        [
+            (4, 8, 68, false),
+            (0, 8, 95, false),
+            (2, 965, 73, false),
+            (1, 965, 15, false),
+            (3, 8, 80, false),
+            (4, 965, 77, false),
+            (0, 8, 54, false),
+            (2, 965, 38, false)
+        ]
+    );
+    assert_eq!(run(42), run(42));
+    assert_ne!(run(42), run(43));
+}
+
+#[test]
+fn test_hunt_world() {
+    use terraria_strings::ItemId;
+    let item = |id: ItemId| req_file::ReqItem {
+        id: id.0,
+        prefix_id: 0,
+    };
+    let mut partial = test_requirement(4, (1, 1));
+    partial.weight = 2.0;
+    let mut group = test_requirement(2, (1, 1));
+    group.items = vec![item(ItemId::ROPE), item(ItemId::MAGIC_MIRROR)];
+    let mut absent = test_requirement(1, (1, 1));
+    absent.items = vec![item(ItemId::HERMES_BOOTS)];
+    absent.weight = 3.0;
+    absent.section = Some("Boots".to_owned());
+    let summary = WorldSummary {
+        stacks: HashMap::from([
+            (i32::from(ItemId::TORCH), 1),
+            (i32::from(ItemId::ROPE), 1),
+            (i32::from(ItemId::MAGIC_MIRROR), 1),
+        ]),
+        inaccessible_chests: Vec::new(),
+    };
+    let report = hunt_world(Path::new("test.wld"), &summary, &[partial, group, absent]);
+    assert!(!report.meets_requirements);
+    // A quarter of the partly met requirement, all of the met group, none of the boots
+    assert_eq!(report.score, 2.0 / 4.0 + 1.0);
+    assert_eq!(report.max_score, 6.0);
+    assert_eq!((report.requirements_met, report.requirements), (1, 3));
+    let missing: Vec<_> = report
+        .missing
+        .iter()
+        .map(|m| (m.section.as_deref(), m.found, m.required))
+        .collect();
+    assert_eq!(missing, [(None, 1, 4), (Some("Boots"), 0, 1)]);
+}
+
+#[test]
+fn test_allocate_spacing_between_requirements() {
+    let mut chests = vec![test_chest(0, 0), test_chest(10, 0), test_chest(100, 0)];
+    let mut free_slots = vec![40; 3];
+    let mut placed_at = Vec::new();
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let first = test_requirement(1, (1, 1));
+    let mut second = test_requirement(1, (1, 1));
+    second.items[0].id = terraria_strings::ItemId::ROPE.0;
+    second.location.spacing = Some(50);
+    let candidates = [0, 1, 2];
+    let (allocated, _) = allocate(
+        &first,
+        &mut chests,
+        &mut free_slots,
+        &mut placed_at,
+        &candidates,
+        &mut rng,
+    );
+    assert_eq!(allocated[0].chest, 0);
+    // A different item still keeps its distance from the torch
+    let (allocated, _) = allocate(
+        &second,
+        &mut chests,
+        &mut free_slots,
+        &mut placed_at,
+        &candidates,
+        &mut rng,
+    );
+    assert_eq!((allocated[0].chest, allocated[0].spilled_over), (2, true));
+    assert_eq!(placed_at, [(0, 100), (100, 100)]);
+}
+
+#[test]
+fn test_allocate_miscounted_free_slots() {
+    let mut chests = vec![test_chest(0, 40)];
+    let mut free_slots = vec![1];
+    let mut placed_at = Vec::new();
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    let req = test_requirement(2, (1, 1));
+    let (allocated, missing) = allocate(
+        &req,
+        &mut chests,
+        &mut free_slots,
+        &mut placed_at,
+        &[0],
+        &mut rng,
+    );
+    assert!(allocated.is_empty());
+    assert_eq!(missing, 2);
+    assert!(placed_at.is_empty());
 }
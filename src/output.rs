@@ -0,0 +1,163 @@
+//! Output formats for command results
+//!
+//! Commands produce a [`Report`] per world, which gets printed as colored text for
+//! humans, or as JSON/CSV with a stable schema for scripts.
+
+use serde::Serialize;
+use std::error::Error;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_lowercase()[..] {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!(
+                "Unknown format '{}', expected json, csv or text",
+                s
+            )),
+        }
+    }
+}
+
+/// The result of a command, printable in every format
+pub trait Report: Serialize {
+    /// Column names of the CSV output
+    const CSV_HEADER: &'static [&'static str];
+    fn print_text(&self);
+    /// The CSV records, with fields in the order of `CSV_HEADER`
+    fn csv_rows(&self) -> Vec<Vec<String>>;
+}
+
+/// Prints reports as they come in.
+///
+/// JSON output is an array of all the reports, CSV output has a single header line
+/// followed by the rows of all the reports. The JSON array is closed when the printer
+/// is dropped, so the output stays valid when a command fails halfway.
+pub struct Printer<W: Write = io::Stdout> {
+    format: Format,
+    n_printed: usize,
+    out: W,
+}
+
+impl Printer {
+    pub fn new(format: Format) -> Self {
+        Self::with_output(format, io::stdout())
+    }
+}
+
+impl<W: Write> Printer<W> {
+    /// Print JSON and CSV to `out` instead of stdout. Text always goes to stdout.
+    pub fn with_output(format: Format, out: W) -> Self {
+        Self {
+            format,
+            n_printed: 0,
+            out,
+        }
+    }
+    pub fn format(&self) -> Format {
+        self.format
+    }
+    pub fn print<R: Report>(&mut self, report: &R) -> Result<(), Box<dyn Error>> {
+        match self.format {
+            Format::Text => report.print_text(),
+            Format::Json => {
+                let json = serde_json::to_string_pretty(report)?;
+                let separator = if self.n_printed == 0 { "[\n" } else { ",\n" };
+                write!(self.out, "{}{}", separator, json)?;
+            }
+            Format::Csv => {
+                if self.n_printed == 0 {
+                    write_csv_row(&mut self.out, R::CSV_HEADER)?;
+                }
+                for row in report.csv_rows() {
+                    write_csv_row(&mut self.out, &row)?;
+                }
+            }
+        }
+        self.n_printed += 1;
+        Ok(())
+    }
+}
+
+impl<W: Write> Drop for Printer<W> {
+    fn drop(&mut self) {
+        if self.format == Format::Json {
+            // Nothing can be done about a failed write here
+            let _ = if self.n_printed == 0 {
+                writeln!(self.out, "[]")
+            } else {
+                writeln!(self.out, "\n]")
+            };
+        }
+    }
+}
+
+fn write_csv_row<S: AsRef<str>>(out: &mut impl Write, fields: &[S]) -> io::Result<()> {
+    let fields: Vec<String> = fields.iter().map(|f| csv_escape(f.as_ref())).collect();
+    writeln!(out, "{}", fields.join(","))
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[test]
+fn test_csv_escape() {
+    assert_eq!(csv_escape("Gold Chest"), "Gold Chest");
+    assert_eq!(csv_escape("10 west, 5 east"), "\"10 west, 5 east\"");
+    assert_eq!(csv_escape("a \"b\""), "\"a \"\"b\"\"\"");
+}
+
+#[cfg(test)]
+#[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+struct TestReport {
+    world: String,
+}
+
+#[cfg(test)]
+impl Report for TestReport {
+    const CSV_HEADER: &'static [&'static str] = &["world"];
+    fn print_text(&self) {}
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        vec![vec![self.world.clone()]]
+    }
+}
+
+#[test]
+fn test_json_closed_on_error() {
+    let mut out = Vec::new();
+    let run = |out: &mut Vec<u8>| -> Result<(), Box<dyn Error>> {
+        let mut printer = Printer::with_output(Format::Json, out);
+        printer.print(&TestReport {
+            world: "a.wld".to_owned(),
+        })?;
+        Err("b.wld: failed to fill whole buffer".into())
+    };
+    assert!(run(&mut out).is_err());
+    let reports: Vec<TestReport> = serde_json::from_slice(&out).unwrap();
+    assert_eq!(
+        reports,
+        [TestReport {
+            world: "a.wld".to_owned()
+        }]
+    );
+    out.clear();
+    drop(Printer::with_output(Format::Json, &mut out));
+    assert_eq!(out, b"[]\n");
+}
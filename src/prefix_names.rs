@@ -87,3 +87,39 @@ static NAMES: &[&str] = &[
 pub fn id_by_name(name: &str) -> Option<u8> {
     NAMES.iter().position(|n| *n == name).map(|p| p as u8 + 1)
 }
+
+pub fn name_by_id(id: u8) -> Option<&'static str> {
+    NAMES.get(usize::from(id).checked_sub(1)?).copied()
+}
+
+/// Prefixes whose names are shared with an earlier one, with the internal names the game
+/// tells them apart by
+const INTERNAL_NAMES: &[(u8, &str)] = &[(43, "Deadly2"), (75, "Hasty2"), (76, "Quick2")];
+
+/// The internal name of a prefix, like `Hasty2`. Unlike the names, these are unique.
+pub fn internal_name_by_id(id: u8) -> Option<&'static str> {
+    match INTERNAL_NAMES.iter().find(|&&(dup_id, _)| dup_id == id) {
+        Some(&(_, name)) => Some(name),
+        None => name_by_id(id),
+    }
+}
+
+pub fn id_by_internal_name(name: &str) -> Option<u8> {
+    match INTERNAL_NAMES
+        .iter()
+        .find(|&&(_, dup_name)| dup_name == name)
+    {
+        Some(&(id, _)) => Some(id),
+        None => id_by_name(name),
+    }
+}
+
+#[test]
+fn test_internal_names() {
+    for id in 1..=NAMES.len() as u8 {
+        let name = internal_name_by_id(id).unwrap();
+        assert_eq!(id_by_internal_name(name), Some(id));
+    }
+    assert_eq!(internal_name_by_id(18), Some("Hasty"));
+    assert_eq!(internal_name_by_id(75), Some("Hasty2"));
+}
@@ -0,0 +1,175 @@
+//! The `render` command, drawing worlds to PNG images
+
+use std::error::Error;
+use std::path::Path;
+use std::str::FromStr;
+use terraria_render::{Image, Layers, Region, Rgba};
+
+/// Things drawn as markers on top of the rendered world
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Overlay {
+    Chests,
+    Spawn,
+    Npcs,
+}
+
+impl FromStr for Overlay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_lowercase()[..] {
+            "chests" => Ok(Overlay::Chests),
+            "spawn" => Ok(Overlay::Spawn),
+            "npcs" => Ok(Overlay::Npcs),
+            _ => Err(format!(
+                "Unknown overlay '{}', expected chests, spawn or npcs",
+                s
+            )),
+        }
+    }
+}
+
+/// Parse a comma separated list of layers, like "tiles,liquids"
+pub fn parse_layers(s: &str) -> Result<Layers, String> {
+    let mut layers = Layers {
+        tiles: false,
+        walls: false,
+        liquids: false,
+        wires: false,
+    };
+    for name in s.split(',') {
+        match &name.trim().to_lowercase()[..] {
+            "tiles" => layers.tiles = true,
+            "walls" => layers.walls = true,
+            "liquids" => layers.liquids = true,
+            "wires" => layers.wires = true,
+            _ => {
+                return Err(format!(
+                    "Unknown layer '{}', expected tiles, walls, liquids or wires",
+                    name
+                ))
+            }
+        }
+    }
+    Ok(layers)
+}
+
+/// Parse a region given as "x,y,width,height" in tiles
+pub fn parse_region(s: &str) -> Result<Region, String> {
+    let parts = s
+        .split(',')
+        .map(|part| part.trim().parse::<u16>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid region '{}': {}", s, e))?;
+    match parts[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Ok(Region {
+            x,
+            y,
+            width,
+            height,
+        }),
+        _ => Err(format!("Invalid region '{}', expected x,y,width,height", s)),
+    }
+}
+
+const CHEST_COLOR: Rgba = [255, 215, 0, 255];
+const SPAWN_COLOR: Rgba = [255, 255, 255, 255];
+const NPC_COLOR: Rgba = [0, 255, 255, 255];
+
+pub fn render(
+    world_path: &Path,
+    out_path: &Path,
+    scale: f64,
+    region: Option<Region>,
+    layers: &Layers,
+    overlays: &[Overlay],
+) -> Result<(), Box<dyn Error>> {
+    if scale.is_nan() || scale <= 0.0 {
+        return Err("Scale must be greater than 0".into());
+    }
+    let (file, base_header) = terraria_wld::open(world_path, false)?;
+    let header = terraria_wld::read_header(&file, base_header.offsets.header as u64)?;
+    let whole_world = Region {
+        x: 0,
+        y: 0,
+        width: header.width,
+        height: header.height,
+    };
+    let region = match region {
+        Some(region) => {
+            if !whole_world.contains(region.x, region.y) {
+                return Err(format!(
+                    "Region starts outside of the world, which is {}x{}",
+                    header.width, header.height
+                )
+                .into());
+            }
+            Region {
+                width: region.width.min(header.width - region.x),
+                height: region.height.min(header.height - region.y),
+                ..region
+            }
+        }
+        None => whole_world,
+    };
+    // Checked before rendering, which takes a while for big worlds
+    terraria_render::scaled_size(region.width.into(), region.height.into(), scale)?;
+    let mut image = terraria_render::render(&file, &base_header, layers, region)?;
+    if scale != 1.0 {
+        image = image.scaled(scale)?;
+    }
+    // Markers are drawn after scaling, so they stay visible in small images
+    let mark = |image: &mut Image, x: f64, y: f64, color| {
+        let x = ((x - f64::from(region.x)) * scale) as i64;
+        let y = ((y - f64::from(region.y)) * scale) as i64;
+        image.draw_marker(x, y, 3, color);
+    };
+    if overlays.contains(&Overlay::Chests) {
+        for chest in crate::read_chests_with_mods(world_path, &file, &base_header)? {
+            // Mark the middle of the 2x2 chest
+            mark(
+                &mut image,
+                f64::from(chest.x) + 1.0,
+                f64::from(chest.y) + 1.0,
+                CHEST_COLOR,
+            );
+        }
+    }
+    if overlays.contains(&Overlay::Npcs) {
+        for npc in terraria_wld::read_npcs(&file, base_header.offsets.npcs as u64)? {
+            // NPC positions are in pixels
+            mark(
+                &mut image,
+                f64::from(npc.x) / 16.0,
+                f64::from(npc.y) / 16.0,
+                NPC_COLOR,
+            );
+        }
+    }
+    if overlays.contains(&Overlay::Spawn) {
+        mark(
+            &mut image,
+            f64::from(header.spawn_x),
+            f64::from(header.spawn_y),
+            SPAWN_COLOR,
+        );
+    }
+    image.write_png(out_path)
+}
+
+#[test]
+fn test_parse_options() {
+    assert_eq!(
+        parse_region("10, 20,300,400"),
+        Ok(Region {
+            x: 10,
+            y: 20,
+            width: 300,
+            height: 400
+        })
+    );
+    assert!(parse_region("10,20,300").is_err());
+    let layers = parse_layers("tiles,wires").unwrap();
+    assert!(layers.tiles && layers.wires && !layers.walls && !layers.liquids);
+    assert!(parse_layers("tiles,dirt").is_err());
+}
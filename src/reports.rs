@@ -0,0 +1,854 @@
+//! The results of the world inspecting commands, see [`crate::output`]
+
+use crate::output::Report;
+use ansi_term::Colour::{Green, Red};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct ItemhuntReport {
+    pub world: String,
+    pub meets_requirements: bool,
+    /// Sum of the weights of the met requirements, and part of the weights of the
+    /// partly met ones
+    pub score: f64,
+    /// The score of a world that meets all requirements
+    pub max_score: f64,
+    pub requirements_met: usize,
+    pub requirements: usize,
+    /// Place among the best scoring worlds, with `--top`
+    pub rank: Option<usize>,
+    pub missing: Vec<MissingItem>,
+}
+
+#[derive(Serialize)]
+pub struct MissingItem {
+    pub item: String,
+    pub section: Option<String>,
+    pub found: u16,
+    pub required: u16,
+}
+
+impl Report for ItemhuntReport {
+    const CSV_HEADER: &'static [&'static str] = &[
+        "world",
+        "meets_requirements",
+        "item",
+        "found",
+        "required",
+        "section",
+        "score",
+        "max_score",
+        "rank",
+    ];
+    fn print_text(&self) {
+        let rank = match self.rank {
+            Some(rank) => format!("#{} ", rank),
+            None => String::new(),
+        };
+        println!(
+            "{}{}: score {:.2}/{:.2}",
+            rank, self.world, self.score, self.max_score
+        );
+        for missing in &self.missing {
+            let section = match &missing.section {
+                Some(section) => format!("[{}] ", section),
+                None => String::new(),
+            };
+            let msg = format!(
+                "{}{} - {}/{}",
+                section, missing.item, missing.found, missing.required
+            );
+            println!("{}", Red.paint(msg));
+        }
+        if self.meets_requirements {
+            println!("{}", Green.paint("This world meets all requirements."));
+        }
+    }
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        // The newer columns come last, so scripts reading the first ones keep working
+        let row = |missing: Option<&MissingItem>| {
+            vec![
+                self.world.clone(),
+                self.meets_requirements.to_string(),
+                missing.map(|m| m.item.clone()).unwrap_or_default(),
+                missing.map(|m| m.found.to_string()).unwrap_or_default(),
+                missing.map(|m| m.required.to_string()).unwrap_or_default(),
+                missing.and_then(|m| m.section.clone()).unwrap_or_default(),
+                self.score.to_string(),
+                self.max_score.to_string(),
+                self.rank.map(|rank| rank.to_string()).unwrap_or_default(),
+            ]
+        };
+        if self.missing.is_empty() {
+            return vec![row(None)];
+        }
+        self.missing.iter().map(|m| row(Some(m))).collect()
+    }
+}
+
+#[derive(Serialize)]
+pub struct BlessReport {
+    pub world: String,
+    /// Blessing again with this seed gives the same result
+    pub seed: u64,
+    /// The world wasn't changed, the placements are only what would happen
+    pub dry_run: bool,
+    pub placements: Vec<Placement>,
+    /// Items that didn't fit in any of their chests, with `--on-full skip`
+    pub unplaced: Vec<Unplaced>,
+}
+
+/// A stack of items put into a chest
+#[derive(Serialize)]
+pub struct Placement {
+    pub item: String,
+    pub stack: u16,
+    pub prefix: Option<String>,
+    pub x: u16,
+    pub y: u16,
+    pub chest_type: String,
+    pub gps: String,
+    /// The chest picked for the stack was full, so it went in the next one with room
+    pub spilled_over: bool,
+}
+
+#[derive(Serialize)]
+pub struct Unplaced {
+    pub item: String,
+    /// Number of stacks that couldn't be placed
+    pub stacks: u16,
+    /// Why they couldn't be placed
+    pub reason: &'static str,
+}
+
+impl Report for BlessReport {
+    const CSV_HEADER: &'static [&'static str] = &[
+        "world",
+        "seed",
+        "dry_run",
+        "item",
+        "stack",
+        "prefix",
+        "x",
+        "y",
+        "chest_type",
+        "gps",
+        "status",
+    ];
+    fn print_text(&self) {
+        let verb = if self.dry_run {
+            "Would place"
+        } else {
+            "Placed"
+        };
+        println!(
+            "{}: {} {} stacks (seed {})",
+            self.world,
+            verb,
+            self.placements.len(),
+            self.seed
+        );
+        if !self.placements.is_empty() {
+            println!(
+                "    {:30}{:>6}  {:12}{:14}position",
+                "item", "stack", "prefix", "chest"
+            );
+        }
+        for p in &self.placements {
+            println!(
+                "    {:30}{:>6}  {:12}{:14}{}{}",
+                p.item,
+                p.stack,
+                p.prefix.as_deref().unwrap_or("-"),
+                p.chest_type,
+                p.gps,
+                if p.spilled_over {
+                    " (spilled over)"
+                } else {
+                    ""
+                }
+            );
+        }
+        for u in &self.unplaced {
+            println!(
+                "    Not placed, {}: {} x{} stacks",
+                u.reason, u.item, u.stacks
+            );
+        }
+    }
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        self.placements
+            .iter()
+            .map(|p| {
+                vec![
+                    self.world.clone(),
+                    self.seed.to_string(),
+                    self.dry_run.to_string(),
+                    p.item.clone(),
+                    p.stack.to_string(),
+                    p.prefix.clone().unwrap_or_default(),
+                    p.x.to_string(),
+                    p.y.to_string(),
+                    p.chest_type.clone(),
+                    p.gps.clone(),
+                    if p.spilled_over {
+                        "spilled over"
+                    } else {
+                        "placed"
+                    }
+                    .to_owned(),
+                ]
+            })
+            .chain(self.unplaced.iter().flat_map(|u| {
+                // One row per stack, like placed stacks
+                (0..u.stacks).map(|_| {
+                    let mut row = vec![String::new(); Self::CSV_HEADER.len()];
+                    row[0] = self.world.clone();
+                    row[1] = self.seed.to_string();
+                    row[2] = self.dry_run.to_string();
+                    row[3] = u.item.clone();
+                    row[10] = "unplaced".to_owned();
+                    row
+                })
+            }))
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+pub struct CurseReport {
+    pub world: String,
+    /// Cursing again with this seed picks the same replacements
+    pub seed: u64,
+    /// The world wasn't changed, the changes are only what would happen
+    pub dry_run: bool,
+    pub changes: Vec<ChestChange>,
+}
+
+/// A stack of items removed from a chest, or replaced with another item
+#[derive(Serialize)]
+pub struct ChestChange {
+    pub item: String,
+    pub prefix: Option<String>,
+    pub stack: u16,
+    /// `None` if the item was removed
+    pub replacement: Option<String>,
+    pub replacement_stack: u16,
+    pub x: u16,
+    pub y: u16,
+    pub chest_type: String,
+    pub gps: String,
+    /// A tModLoader item. These are only reported, the `.twld` file isn't changed.
+    pub modded: bool,
+}
+
+impl Report for CurseReport {
+    const CSV_HEADER: &'static [&'static str] = &[
+        "world",
+        "seed",
+        "dry_run",
+        "item",
+        "prefix",
+        "stack",
+        "replacement",
+        "replacement_stack",
+        "x",
+        "y",
+        "chest_type",
+        "gps",
+        "modded",
+    ];
+    fn print_text(&self) {
+        let modded = self.changes.iter().filter(|c| c.modded).count();
+        let removed = self
+            .changes
+            .iter()
+            .filter(|c| !c.modded && c.replacement.is_none())
+            .count();
+        let replaced = self.changes.len() - modded - removed;
+        let verb = if self.dry_run {
+            "Would remove"
+        } else {
+            "Removed"
+        };
+        println!(
+            "{}: {} {} stacks and replace {} (seed {})",
+            self.world, verb, removed, replaced, self.seed
+        );
+        if modded > 0 {
+            println!(
+                "    {} modded stacks also match, but can't be changed",
+                modded
+            );
+        }
+        for c in &self.changes {
+            let item = match &c.prefix {
+                Some(prefix) => format!("{} {}", prefix, c.item),
+                None => c.item.clone(),
+            };
+            let change = match &c.replacement {
+                _ if c.modded => "modded, not changed".to_owned(),
+                Some(replacement) => format!("-> {} x{}", replacement, c.replacement_stack),
+                None => "removed".to_owned(),
+            };
+            println!(
+                "    {:30}{:>6}  {:30}{:14}{}",
+                item, c.stack, change, c.chest_type, c.gps
+            );
+        }
+    }
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        self.changes
+            .iter()
+            .map(|c| {
+                vec![
+                    self.world.clone(),
+                    self.seed.to_string(),
+                    self.dry_run.to_string(),
+                    c.item.clone(),
+                    c.prefix.clone().unwrap_or_default(),
+                    c.stack.to_string(),
+                    c.replacement.clone().unwrap_or_default(),
+                    c.replacement_stack.to_string(),
+                    c.x.to_string(),
+                    c.y.to_string(),
+                    c.chest_type.clone(),
+                    c.gps.clone(),
+                    c.modded.to_string(),
+                ]
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+pub struct FindReport {
+    pub world: String,
+    pub item: String,
+    pub chests: Vec<ChestLocation>,
+}
+
+#[derive(Serialize)]
+pub struct ChestLocation {
+    pub x: u16,
+    pub y: u16,
+    /// Position as shown by the GPS, like "100 west, 20 below surface"
+    pub gps: String,
+}
+
+impl Report for FindReport {
+    const CSV_HEADER: &'static [&'static str] = &["world", "item", "x", "y", "gps"];
+    fn print_text(&self) {
+        for chest in &self.chests {
+            println!("Found in chest at {}", chest.gps);
+        }
+    }
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        self.chests
+            .iter()
+            .map(|chest| {
+                vec![
+                    self.world.clone(),
+                    self.item.clone(),
+                    chest.x.to_string(),
+                    chest.y.to_string(),
+                    chest.gps.clone(),
+                ]
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+pub struct FindTileReport {
+    pub world: String,
+    pub tile: String,
+    pub objects: Vec<TileLocation>,
+}
+
+/// An object, or a group of touching tiles, with the position of its top left corner
+#[derive(Serialize)]
+pub struct TileLocation {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+    /// Only known for objects
+    pub style: Option<u16>,
+    pub gps: String,
+}
+
+impl Report for FindTileReport {
+    const CSV_HEADER: &'static [&'static str] =
+        &["world", "tile", "x", "y", "width", "height", "style", "gps"];
+    fn print_text(&self) {
+        for object in &self.objects {
+            match object.style {
+                Some(style) => println!("{} (style {}) at {}", self.tile, style, object.gps),
+                None => println!("{} at {}", self.tile, object.gps),
+            }
+        }
+        println!("{} found: {}", self.tile, self.objects.len());
+    }
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        self.objects
+            .iter()
+            .map(|object| {
+                vec![
+                    self.world.clone(),
+                    self.tile.clone(),
+                    object.x.to_string(),
+                    object.y.to_string(),
+                    object.width.to_string(),
+                    object.height.to_string(),
+                    object.style.map(|s| s.to_string()).unwrap_or_default(),
+                    object.gps.clone(),
+                ]
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+pub struct StructuresReport {
+    pub world: String,
+    pub dungeon: Option<DungeonReport>,
+    pub structures: Vec<StructureLocation>,
+}
+
+#[derive(Serialize)]
+pub struct DungeonReport {
+    pub side: &'static str,
+    pub color: &'static str,
+    pub entrance_x: i32,
+    pub entrance_y: i32,
+    pub entrance_gps: String,
+}
+
+/// The bounding rectangle of a structure, with `gps` being its top left corner
+#[derive(Serialize)]
+pub struct StructureLocation {
+    pub kind: &'static str,
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+    pub tiles: u32,
+    pub gps: String,
+}
+
+impl Report for StructuresReport {
+    const CSV_HEADER: &'static [&'static str] =
+        &["world", "kind", "x", "y", "width", "height", "tiles", "gps"];
+    fn print_text(&self) {
+        println!("{}:", self.world);
+        match &self.dungeon {
+            Some(dungeon) => println!(
+                "    Dungeon: {} side, {} bricks, entrance at {}",
+                dungeon.side, dungeon.color, dungeon.entrance_gps
+            ),
+            None => println!("    No dungeon found"),
+        }
+        for s in &self.structures {
+            println!(
+                "    {} at {} ({}x{} tiles)",
+                s.kind, s.gps, s.width, s.height
+            );
+        }
+    }
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        self.structures
+            .iter()
+            .map(|s| {
+                vec![
+                    self.world.clone(),
+                    s.kind.to_owned(),
+                    s.x.to_string(),
+                    s.y.to_string(),
+                    s.width.to_string(),
+                    s.height.to_string(),
+                    s.tiles.to_string(),
+                    s.gps.clone(),
+                ]
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+pub struct ChestInfoReport {
+    pub world: String,
+    pub x: u16,
+    pub y: u16,
+    /// `None` if there is no chest at the position
+    pub chest: Option<ChestContents>,
+}
+
+#[derive(Serialize)]
+pub struct ChestContents {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub items: Vec<ReportItem>,
+}
+
+#[derive(Serialize)]
+pub struct ReportItem {
+    pub name: String,
+    pub stack: u16,
+    pub prefix: Option<String>,
+}
+
+impl Report for ChestInfoReport {
+    const CSV_HEADER: &'static [&'static str] =
+        &["world", "x", "y", "type", "item", "stack", "prefix"];
+    fn print_text(&self) {
+        match &self.chest {
+            Some(chest) => {
+                println!("{} Chest containing: ", chest.type_);
+                for item in &chest.items {
+                    match &item.prefix {
+                        Some(prefix) => println!("{} {} {}", item.stack, prefix, item.name),
+                        None => println!("{} {}", item.stack, item.name),
+                    }
+                }
+            }
+            None => println!("No chest at {}, {}", self.x, self.y),
+        }
+    }
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        let chest = match &self.chest {
+            Some(chest) => chest,
+            None => return Vec::new(),
+        };
+        chest
+            .items
+            .iter()
+            .map(|item| {
+                vec![
+                    self.world.clone(),
+                    self.x.to_string(),
+                    self.y.to_string(),
+                    chest.type_.clone(),
+                    item.name.clone(),
+                    item.stack.to_string(),
+                    item.prefix.clone().unwrap_or_default(),
+                ]
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+pub struct AnalyzeChestsReport {
+    pub world: String,
+    pub chests_containing_something: u32,
+    /// Sorted by number of stacks, most common first
+    pub items: Vec<ItemStat>,
+}
+
+#[derive(Serialize)]
+pub struct ItemStat {
+    pub name: String,
+    pub stacks: u32,
+    pub total: u32,
+}
+
+impl Report for AnalyzeChestsReport {
+    const CSV_HEADER: &'static [&'static str] = &["world", "item", "stacks", "total"];
+    fn print_text(&self) {
+        println!("{:30}stack total", "name");
+        for stat in &self.items {
+            println!("{:30}{:<5} {}", stat.name, stat.stacks, stat.total);
+        }
+        println!(
+            "{} total chests that contain something",
+            self.chests_containing_something
+        );
+    }
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        self.items
+            .iter()
+            .map(|stat| {
+                vec![
+                    self.world.clone(),
+                    stat.name.clone(),
+                    stat.stacks.to_string(),
+                    stat.total.to_string(),
+                ]
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+pub struct BiomeReport {
+    pub world: String,
+    pub solid_blocks: u32,
+    /// Width of the sections in tiles, if the world was split into sections
+    pub section_width: Option<u16>,
+    pub biomes: Vec<BiomeStat>,
+}
+
+/// The share of a biome among solid blocks, in percent
+#[derive(Serialize)]
+pub struct BiomeStat {
+    pub biome: &'static str,
+    pub blocks: u32,
+    pub percent: f64,
+    pub layers: LayerPercents,
+    /// From west to east
+    pub sections: Vec<f64>,
+}
+
+#[derive(Serialize)]
+pub struct LayerPercents {
+    pub surface: f64,
+    pub underground: f64,
+    pub cavern: f64,
+    pub underworld: f64,
+}
+
+impl Report for BiomeReport {
+    /// One row per biome and scope, where the scope is "total", a layer,
+    /// or a section like "section 3"
+    const CSV_HEADER: &'static [&'static str] = &["world", "biome", "scope", "percent"];
+    fn print_text(&self) {
+        println!("{} ({} solid blocks):", self.world, self.solid_blocks);
+        println!(
+            "    {:12}{:>8}{:>10}{:>13}{:>8}{:>12}",
+            "biome", "total", "surface", "underground", "cavern", "underworld"
+        );
+        for stat in &self.biomes {
+            let l = &stat.layers;
+            println!(
+                "    {:12}{:>7.2}%{:>9.2}%{:>12.2}%{:>7.2}%{:>11.2}%",
+                stat.biome, stat.percent, l.surface, l.underground, l.cavern, l.underworld
+            );
+        }
+        if let Some(width) = self.section_width {
+            println!("    Sections of {} tiles, west to east:", width);
+            for stat in &self.biomes {
+                let sections: Vec<String> = stat
+                    .sections
+                    .iter()
+                    .map(|percent| format!("{:>6.1}", percent))
+                    .collect();
+                println!("    {:12}{}", stat.biome, sections.join(""));
+            }
+        }
+    }
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        let mut rows = Vec::new();
+        for stat in &self.biomes {
+            let l = &stat.layers;
+            let mut row = |scope: String, percent: f64| {
+                rows.push(vec![
+                    self.world.clone(),
+                    stat.biome.to_owned(),
+                    scope,
+                    percent.to_string(),
+                ])
+            };
+            row("total".into(), stat.percent);
+            row("surface".into(), l.surface);
+            row("underground".into(), l.underground);
+            row("cavern".into(), l.cavern);
+            row("underworld".into(), l.underworld);
+            for (i, &percent) in stat.sections.iter().enumerate() {
+                row(format!("section {}", i + 1), percent);
+            }
+        }
+        rows
+    }
+}
+
+#[derive(Serialize)]
+pub struct TileStatsReport {
+    pub world: String,
+    /// Print the per layer counts in the text output, they're always in JSON and CSV
+    #[serde(skip)]
+    pub show_layers: bool,
+    /// Tiles first, then walls, then liquids, most common first
+    pub stats: Vec<TileStat>,
+    /// Left out of the CSV output, since they follow from the ore counts
+    pub ore_alternatives: Vec<OreAlternative>,
+}
+
+#[derive(Serialize)]
+pub struct TileStat {
+    /// "tile", "wall" or "liquid"
+    pub kind: &'static str,
+    /// `None` for liquids
+    pub id: Option<u16>,
+    /// Only set for tiles that are counted per style, like gems and bars
+    pub style: Option<u16>,
+    pub name: String,
+    pub total: u32,
+    pub layers: LayerCounts,
+}
+
+#[derive(Serialize)]
+pub struct LayerCounts {
+    pub surface: u32,
+    pub underground: u32,
+    pub cavern: u32,
+    pub underworld: u32,
+}
+
+/// Which ores of a pair like copper/tin the world has
+#[derive(Serialize)]
+pub struct OreAlternative {
+    pub ores: &'static str,
+    pub generated: Vec<String>,
+}
+
+impl Report for TileStatsReport {
+    const CSV_HEADER: &'static [&'static str] = &[
+        "world",
+        "kind",
+        "id",
+        "style",
+        "name",
+        "total",
+        "surface",
+        "underground",
+        "cavern",
+        "underworld",
+    ];
+    fn print_text(&self) {
+        println!("{}:", self.world);
+        if self.show_layers {
+            println!(
+                "    {:30}{:>10}{:>10}{:>12}{:>10}{:>12}",
+                "name", "total", "surface", "underground", "cavern", "underworld"
+            );
+        }
+        for stat in &self.stats {
+            if self.show_layers {
+                let l = &stat.layers;
+                println!(
+                    "    {:30}{:>10}{:>10}{:>12}{:>10}{:>12}",
+                    stat.name, stat.total, l.surface, l.underground, l.cavern, l.underworld
+                );
+            } else {
+                println!("    {}: {}", stat.name, stat.total);
+            }
+        }
+        println!("    Ores:");
+        for alternative in &self.ore_alternatives {
+            let generated = if alternative.generated.is_empty() {
+                "none".to_owned()
+            } else {
+                alternative.generated.join(", ")
+            };
+            println!("        {}: {}", alternative.ores, generated);
+        }
+    }
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        let opt = |v: Option<u16>| v.map(|v| v.to_string()).unwrap_or_default();
+        self.stats
+            .iter()
+            .map(|stat| {
+                vec![
+                    self.world.clone(),
+                    stat.kind.to_owned(),
+                    opt(stat.id),
+                    opt(stat.style),
+                    stat.name.clone(),
+                    stat.total.to_string(),
+                    stat.layers.surface.to_string(),
+                    stat.layers.underground.to_string(),
+                    stat.layers.cavern.to_string(),
+                    stat.layers.underworld.to_string(),
+                ]
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+pub struct InfoReport {
+    pub world: String,
+    pub name: String,
+    pub seed: String,
+    pub generator_version: i64,
+    pub guid: String,
+    pub world_id: i32,
+    pub width: u16,
+    pub height: u16,
+    pub size_class: &'static str,
+    pub game_mode: &'static str,
+    pub evil: &'static str,
+    pub hardmode: bool,
+    pub spawn_x: i32,
+    pub spawn_y: i32,
+    pub surface_y: f64,
+    pub rock_y: f64,
+    pub times_saved: u32,
+    pub version: i32,
+}
+
+impl Report for InfoReport {
+    const CSV_HEADER: &'static [&'static str] = &[
+        "world",
+        "name",
+        "seed",
+        "generator_version",
+        "guid",
+        "world_id",
+        "width",
+        "height",
+        "size_class",
+        "game_mode",
+        "evil",
+        "hardmode",
+        "spawn_x",
+        "spawn_y",
+        "surface_y",
+        "rock_y",
+        "times_saved",
+        "version",
+    ];
+    fn print_text(&self) {
+        println!("{}:", self.world);
+        println!("    Name: {}", self.name);
+        println!("    Seed: {}", self.seed);
+        println!("    Generator version: {}", self.generator_version);
+        println!("    GUID: {}", self.guid);
+        println!("    World id: {}", self.world_id);
+        println!(
+            "    Size: {}x{} ({})",
+            self.width, self.height, self.size_class
+        );
+        println!("    Game mode: {}", self.game_mode);
+        println!("    Evil: {}", self.evil);
+        println!("    Hardmode: {}", if self.hardmode { "yes" } else { "no" });
+        println!("    Spawn: {}, {}", self.spawn_x, self.spawn_y);
+        println!("    Surface level: {}", self.surface_y);
+        println!("    Rock level: {}", self.rock_y);
+        println!("    Times saved: {}", self.times_saved);
+        println!("    Version: {}", self.version);
+    }
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        vec![vec![
+            self.world.clone(),
+            self.name.clone(),
+            self.seed.clone(),
+            self.generator_version.to_string(),
+            self.guid.clone(),
+            self.world_id.to_string(),
+            self.width.to_string(),
+            self.height.to_string(),
+            self.size_class.to_owned(),
+            self.game_mode.to_owned(),
+            self.evil.to_owned(),
+            self.hardmode.to_string(),
+            self.spawn_x.to_string(),
+            self.spawn_y.to_string(),
+            self.surface_y.to_string(),
+            self.rock_y.to_string(),
+            self.times_saved.to_string(),
+            self.version.to_string(),
+        ]]
+    }
+}
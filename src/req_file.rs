@@ -1,24 +1,108 @@
 //! Requirements file parsing
+//!
+//! Every line is a requirement, like `*Menacing Hermes Boots | Flurry Boots: gold, 2`.
+//! Lines can also start a `[section]`, which the requirements after it belong to, or
+//! `include other.list`, relative to the file it's in.
 
+use crate::biomes::Biome;
 use std::error::Error;
-use std::fs::File;
-use std::io::prelude::*;
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
 use terraria_strings::ItemIdMap;
-use terraria_wld::ChestType;
+use terraria_wld::{ChestType, Layer, XSide};
+
+/// One of the items that meet a requirement
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ReqItem {
+    pub id: u16,
+    pub prefix_id: u8,
+}
 
 #[derive(Debug, PartialEq)]
 pub struct Requirement<Tracker: Default> {
-    pub id: u16,
+    /// Any of these items meets the requirement, stacks of them all count together
+    pub items: Vec<ReqItem>,
     pub n_stacks: u16,
+    /// Stack sizes are picked from `min_per_stack` to `max_per_stack`, both included
     pub min_per_stack: u16,
     pub max_per_stack: u16,
     pub only_in: Vec<ChestType>,
+    pub location: Location,
     /// Embedded tracker that you can use to associate tracking data with this requirement.
     /// For example, you could track how many times the item has been found.
     /// You can use `()` if you don't need tracking.
     pub tracker: Tracker,
-    pub prefix_id: u8,
+    /// How much the requirement counts for when scoring worlds
+    pub weight: f64,
+    /// The `[section]` the requirement is in
+    pub section: Option<String>,
+}
+
+/// Where in the world an item may be placed. Empty lists and `None` mean anywhere.
+#[derive(Debug, PartialEq, Default)]
+pub struct Location {
+    pub layers: Vec<Layer>,
+    /// The biome most of the blocks around the chest belong to
+    pub biomes: Vec<Biome>,
+    /// Side of the spawn point
+    pub side: Option<XSide>,
+    /// Distance from the spawn point in tiles
+    pub min_distance: Option<u16>,
+    pub max_distance: Option<u16>,
+    /// Minimum distance in tiles from every other stack bless-chests placed, of any item
+    pub spacing: Option<u16>,
+}
+
+impl Location {
+    /// Set a constraint from a `key=value` segment, like `layer=cavern/underworld`
+    fn set(&mut self, key: &str, value: &str) -> Result<(), Box<dyn Error>> {
+        let duplicate = || format!("Duplicate {} segment", key);
+        match key {
+            "layer" if !self.layers.is_empty() => return Err(duplicate().into()),
+            "layer" => self.layers = parse_names(value, &Layer::ALL, Layer::name)?,
+            "biome" if !self.biomes.is_empty() => return Err(duplicate().into()),
+            "biome" => self.biomes = parse_names(value, &Biome::ALL, Biome::name)?,
+            "side" if self.side.is_some() => return Err(duplicate().into()),
+            "side" => {
+                self.side = Some(match &value.to_lowercase()[..] {
+                    "west" => XSide::West,
+                    "east" => XSide::East,
+                    _ => return Err(format!("Invalid side: {}", value).into()),
+                })
+            }
+            "min-distance" | "max-distance" | "spacing" => {
+                let field = match key {
+                    "min-distance" => &mut self.min_distance,
+                    "max-distance" => &mut self.max_distance,
+                    _ => &mut self.spacing,
+                };
+                if field.is_some() {
+                    return Err(duplicate().into());
+                }
+                *field = Some(value.parse()?);
+            }
+            _ => return Err(format!("Unknown constraint: {}", key).into()),
+        }
+        Ok(())
+    }
+}
+
+/// Parse a list of names separated by `/`, like `snow/jungle`
+fn parse_names<T: Copy>(
+    value: &str,
+    all: &[T],
+    name: impl Fn(T) -> &'static str,
+) -> Result<Vec<T>, String> {
+    value
+        .split('/')
+        .map(|v| {
+            let v = v.trim().to_lowercase();
+            all.iter()
+                .copied()
+                .find(|&t| name(t) == v)
+                .ok_or_else(|| format!("Invalid value: {}", v))
+        })
+        .collect()
 }
 
 enum Segment {
@@ -49,6 +133,9 @@ pub fn chest_type_from_name(name: &str) -> Option<terraria_wld::ChestType> {
         "locked jungle" => LockedJungle,
         "locked frozen" => LockedFrozen,
         "lihzahrd" => Lihzahrd,
+        "sandstone" => Sandstone,
+        "dead man's" | "dead mans" => DeadMans,
+        "locked desert" => LockedDesert,
         _ => return None,
     })
 }
@@ -70,10 +157,14 @@ fn parse_n_stacks_or_stack_range(seg: &str) -> Result<Segment, Box<dyn Error>> {
     let seg = seg.trim();
     Ok(match seg.find('-') {
         None => Segment::NStacks(seg.parse()?),
-        Some(hyphen) => Segment::StackRange(
-            seg[..hyphen].trim().parse()?,
-            seg[hyphen + 1..].trim().parse()?,
-        ),
+        Some(hyphen) => {
+            let min = seg[..hyphen].trim().parse()?;
+            let max = seg[hyphen + 1..].trim().parse()?;
+            if min == 0 || min > max {
+                return Err(format!("Invalid stack range: {}", seg).into());
+            }
+            Segment::StackRange(min, max)
+        }
     })
 }
 
@@ -85,65 +176,120 @@ fn parse_segment(seg: &str) -> Result<Segment, Box<dyn Error>> {
     }
 }
 
+/// The segments after the colon of a line. Everything is optional.
+#[derive(Default)]
+pub struct Segments {
+    pub n_stacks: Option<u16>,
+    pub stack_range: Option<(u16, u16)>,
+    pub only_in: Option<Vec<ChestType>>,
+    pub location: Location,
+    pub weight: Option<f64>,
+}
+
+/// Parse comma separated segments, like `gold/ivy, 2, 3-7, layer=cavern`
+pub fn parse_segments(segments: &str) -> Result<Segments, Box<dyn Error>> {
+    let mut parsed = Segments::default();
+    for seg in segments.split(',') {
+        let seg = seg.trim();
+        if seg.is_empty() {
+            continue;
+        }
+        match seg.split_once('=') {
+            Some((key, _)) if key.trim() == "weight" && parsed.weight.is_some() => {
+                return Err("Duplicate weight segment".into())
+            }
+            Some((key, value)) if key.trim() == "weight" => {
+                let weight: f64 = value.trim().parse()?;
+                if !(weight >= 0.0 && weight.is_finite()) {
+                    return Err(format!("Invalid weight: {}", value.trim()).into());
+                }
+                parsed.weight = Some(weight);
+                continue;
+            }
+            Some((key, value)) => {
+                parsed.location.set(key.trim(), value.trim())?;
+                continue;
+            }
+            None => {}
+        }
+        match parse_segment(seg)? {
+            Segment::NStacks(n) => match parsed.n_stacks {
+                None => parsed.n_stacks = Some(n),
+                Some(_) => return Err("Duplicate n-stacks segment".into()),
+            },
+            Segment::StackRange(min, max) => match parsed.stack_range {
+                None => parsed.stack_range = Some((min, max)),
+                Some(_) => return Err("Duplicate stack range segment".into()),
+            },
+            Segment::OnlyIn(vec) => match parsed.only_in {
+                None => parsed.only_in = Some(vec),
+                Some(_) => return Err("Duplicate only-in segment".into()),
+            },
+        }
+    }
+    Ok(parsed)
+}
+
+/// Split the `*Prefix` off the start of a line, if there is one.
+///
+/// Returns the prefix id and the rest of the line, which is empty if there's only a prefix.
+pub fn split_prefix(line: &str) -> Result<(Option<u8>, &str), Box<dyn Error>> {
+    let rest = match line.strip_prefix('*') {
+        Some(rest) => rest,
+        None => return Ok((None, line)),
+    };
+    let (prefix, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+    let prefix_id = crate::prefix_names::id_by_name(prefix)
+        .ok_or_else(|| format!("Invalid prefix: {}", prefix))?;
+    Ok((Some(prefix_id), rest.trim_start()))
+}
+
 impl<Tracker: Default> Requirement<Tracker> {
     fn parse(line: &str, id_map: &ItemIdMap) -> Result<Self, Box<dyn Error>> {
-        let prefix_id;
-        let from_name = if line.starts_with('*') {
-            let first_space = line.find(' ').ok_or("Expected space after *Prefix")?;
-            let prefix = &line[1..first_space];
-            prefix_id = crate::prefix_names::id_by_name(prefix)
-                .ok_or_else(|| format!("Invalid prefix: {}", prefix))?;
-            &line[first_space + 1..]
-        } else {
-            prefix_id = 0;
-            line
+        let (names, segments) = match line.split_once(':') {
+            Some((names, segments)) => (names, parse_segments(segments)?),
+            None => (line, Segments::default()),
         };
-        let mut n_stacks = None;
-        let mut stack_range = None;
-        let mut only_in = None;
-        let end_of_name;
-        if let Some(colon) = from_name.find(':') {
-            let segments = from_name[colon + 1..].split(',');
-            for seg in segments {
-                let seg = seg.trim();
-                if seg.is_empty() {
-                    continue;
-                }
-                match parse_segment(seg)? {
-                    Segment::NStacks(n) => match n_stacks {
-                        None => n_stacks = Some(n),
-                        Some(_) => return Err("Duplicate n-stacks segment".into()),
-                    },
-                    Segment::StackRange(min, max) => match stack_range {
-                        None => stack_range = Some((min, max)),
-                        Some(_) => return Err("Duplicate stack range segment".into()),
-                    },
-                    Segment::OnlyIn(vec) => match only_in {
-                        None => only_in = Some(vec),
-                        Some(_) => return Err("Duplicate only-in segment".into()),
-                    },
+        let items = names
+            .split('|')
+            .map(|name| {
+                let (prefix_id, name) = split_prefix(name.trim())?;
+                if name.is_empty() {
+                    return Err("Expected an item after *Prefix".into());
                 }
-            }
-            end_of_name = colon;
-        } else {
-            end_of_name = from_name.len();
-        }
-        let (min, max) = stack_range.unwrap_or((1, 1));
-        let only_in = only_in.unwrap_or_default();
+                Ok(ReqItem {
+                    id: id_map
+                        .id_by_any_name(name)
+                        .ok_or_else(|| crate::unknown_item_msg(name, id_map))?,
+                    prefix_id: prefix_id.unwrap_or(0),
+                })
+            })
+            .collect::<Result<_, Box<dyn Error>>>()?;
+        let (min, max) = segments.stack_range.unwrap_or((1, 1));
         Ok(Requirement {
-            id: id_map
-                .id_by_name(&from_name[..end_of_name])
-                .ok_or_else(|| {
-                    format!("No matching id for item '{}'", &from_name[..end_of_name])
-                })?,
-            n_stacks: n_stacks.unwrap_or(1),
+            items,
+            n_stacks: segments.n_stacks.unwrap_or(1),
             min_per_stack: min,
             max_per_stack: max,
-            only_in,
+            only_in: segments.only_in.unwrap_or_default(),
+            location: segments.location,
             tracker: Default::default(),
-            prefix_id,
+            weight: segments.weight.unwrap_or(1.0),
+            section: None,
         })
     }
+    /// Whether an item with this id meets the requirement
+    pub fn matches(&self, id: i32) -> bool {
+        self.items.iter().any(|item| i32::from(item.id) == id)
+    }
+    /// The names of the items, like `Hermes Boots | Flurry Boots`
+    pub fn name(&self, id_map: &ItemIdMap) -> String {
+        self.items
+            .iter()
+            .map(|item| id_map.name_by_id(item.id).unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
 }
 
 #[test]
@@ -152,17 +298,22 @@ fn test_parse() {
     assert_eq!(
         Requirement::parse(
             "Sandstorm in a Bottle: gold/locked shadow, 2, 3-7",
-            &item_ids
+            item_ids
         )
         .unwrap(),
         Requirement {
-            id: 857,
+            items: vec![ReqItem {
+                id: 857,
+                prefix_id: 0
+            }],
             n_stacks: 2,
             min_per_stack: 3,
             max_per_stack: 7,
             only_in: vec![ChestType::Gold, ChestType::LockedShadow],
+            location: Location::default(),
             tracker: (),
-            prefix_id: 0,
+            weight: 1.0,
+            section: None,
         }
     )
 }
@@ -171,41 +322,169 @@ fn test_parse() {
 fn test_parse_no_extra() {
     let item_ids = terraria_strings::item_ids();
     assert_eq!(
-        Requirement::parse("Sandstorm in a Bottle", &item_ids).unwrap(),
+        Requirement::parse("Sandstorm in a Bottle", item_ids).unwrap(),
         Requirement {
-            id: 857,
+            items: vec![ReqItem {
+                id: 857,
+                prefix_id: 0
+            }],
             n_stacks: 1,
             min_per_stack: 1,
             max_per_stack: 1,
             only_in: vec![],
+            location: Location::default(),
             tracker: (),
-            prefix_id: 0,
+            weight: 1.0,
+            section: None,
         }
     )
 }
 
+#[test]
+fn test_parse_location() {
+    let item_ids = terraria_strings::item_ids();
+    let req = Requirement::<()>::parse(
+        "Hermes Boots: gold, 2, layer=underground/cavern, biome=snow, side=east, \
+         min-distance=300, spacing=200",
+        item_ids,
+    )
+    .unwrap();
+    assert_eq!(
+        req.location,
+        Location {
+            layers: vec![Layer::Underground, Layer::Cavern],
+            biomes: vec![Biome::Snow],
+            side: Some(XSide::East),
+            min_distance: Some(300),
+            max_distance: None,
+            spacing: Some(200),
+        }
+    );
+    assert!(Requirement::<()>::parse("Hermes Boots: gold, layer=sky", item_ids).is_err());
+    assert!(
+        Requirement::<()>::parse("Hermes Boots: gold, side=west, side=east", item_ids).is_err()
+    );
+}
+
+#[test]
+fn test_parse_invalid_range() {
+    let item_ids = terraria_strings::item_ids();
+    assert!(Requirement::<()>::parse("Sandstorm in a Bottle: gold, 1, 7-3", item_ids).is_err());
+    assert!(Requirement::<()>::parse("Sandstorm in a Bottle: gold, 1, 0-3", item_ids).is_err());
+}
+
 pub fn from_path<Tracker: Default>(
     path: &Path,
     id_map: &ItemIdMap,
 ) -> Result<Vec<Requirement<Tracker>>, Box<dyn Error>> {
-    let mut file = File::open(path)?;
-    let mut buf = String::new();
-    file.read_to_string(&mut buf)?;
-    from_str(&buf, id_map)
+    let mut reqs = Vec::new();
+    read_file(path, id_map, &mut Vec::new(), &mut reqs)?;
+    Ok(reqs)
+}
+
+/// `including` are the files currently being read, to catch files including themselves
+fn read_file<Tracker: Default>(
+    path: &Path,
+    id_map: &ItemIdMap,
+    including: &mut Vec<PathBuf>,
+    reqs: &mut Vec<Requirement<Tracker>>,
+) -> Result<(), Box<dyn Error>> {
+    let err = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+    let canonical = path.canonicalize().map_err(|e| err(&e))?;
+    if including.contains(&canonical) {
+        return Err(format!("{} includes itself", path.display()).into());
+    }
+    let txt = fs::read_to_string(path).map_err(|e| err(&e))?;
+    including.push(canonical);
+    let dir = path.parent().unwrap_or(Path::new(""));
+    parse_lines(&txt, dir, id_map, including, reqs).map_err(|e| err(&e))?;
+    including.pop();
+    Ok(())
 }
 
-pub fn from_str<Tracker: Default>(
+fn parse_lines<Tracker: Default>(
     txt: &str,
+    dir: &Path,
     id_map: &ItemIdMap,
-) -> Result<Vec<Requirement<Tracker>>, Box<dyn Error>> {
-    let mut reqs = Vec::new();
+    including: &mut Vec<PathBuf>,
+    reqs: &mut Vec<Requirement<Tracker>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut section = None;
     for (n, line) in txt.lines().enumerate() {
         let line = line.trim();
-        if !(line.is_empty() || line.starts_with('#')) {
-            reqs.push(
-                Requirement::parse(line, id_map).map_err(|e| format!("Line {}: {}", n + 1, e))?,
-            );
+        if line.is_empty() || line.starts_with('#') {
+            continue;
         }
+        let result = if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = Some(name.trim().to_owned());
+            Ok(())
+        } else if let Some(include) = line.strip_prefix("include ") {
+            let start = reqs.len();
+            let result = read_file(&dir.join(include.trim()), id_map, including, reqs);
+            // Included requirements outside of a section go in the current one
+            for req in &mut reqs[start..] {
+                if req.section.is_none() {
+                    req.section.clone_from(&section);
+                }
+            }
+            result
+        } else {
+            Requirement::parse(line, id_map).map(|req| {
+                reqs.push(Requirement {
+                    section: section.clone(),
+                    ..req
+                })
+            })
+        };
+        result.map_err(|e| format!("Line {}: {}", n + 1, e))?;
     }
-    Ok(reqs)
+    Ok(())
+}
+
+#[test]
+fn test_groups_and_sections() {
+    let item_ids = terraria_strings::item_ids();
+    let mut reqs = Vec::<Requirement<()>>::new();
+    parse_lines(
+        "Magic Mirror\n\
+         [mobility]\n\
+         *Quick Hermes Boots | Flurry Boots: gold, weight=2.5\n",
+        Path::new(""),
+        item_ids,
+        &mut Vec::new(),
+        &mut reqs,
+    )
+    .unwrap();
+    assert_eq!(reqs[0].section, None);
+    let boots = &reqs[1];
+    assert_eq!(boots.section.as_deref(), Some("mobility"));
+    assert_eq!(boots.weight, 2.5);
+    assert_eq!(boots.name(item_ids), "Hermes Boots | Flurry Boots");
+    use terraria_strings::ItemId;
+    assert!(boots.matches(ItemId::HERMES_BOOTS.into()));
+    assert!(boots.matches(ItemId::FLURRY_BOOTS.into()));
+    assert!(!boots.matches(ItemId::MAGIC_MIRROR.into()));
+    assert_ne!(boots.items[0].prefix_id, 0);
+    assert_eq!(boots.items[1].prefix_id, 0);
+}
+
+#[test]
+fn test_include() {
+    let dir = std::env::temp_dir().join(format!("wldmania-include-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("main.list"),
+        "[boots]\ninclude boots.list\nMagic Mirror\n",
+    )
+    .unwrap();
+    fs::write(dir.join("boots.list"), "Hermes Boots\n").unwrap();
+    fs::write(dir.join("loop.list"), "include loop.list\n").unwrap();
+    let item_ids = terraria_strings::item_ids();
+    let reqs = from_path::<()>(&dir.join("main.list"), item_ids).unwrap();
+    assert_eq!(reqs.len(), 2);
+    assert!(reqs
+        .iter()
+        .all(|req| req.section.as_deref() == Some("boots")));
+    assert!(from_path::<()>(&dir.join("loop.list"), item_ids).is_err());
+    fs::remove_dir_all(&dir).unwrap();
 }
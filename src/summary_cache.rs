@@ -0,0 +1,120 @@
+//! Chest summaries of worlds for `itemhunt`, cached in a file between runs
+//!
+//! Summaries are keyed by the GUID of the world, how many times it was saved, and the
+//! size and modification time of the file, so a world that changed since it was
+//! summarized gets summarized again. The game bumps the save count, but other tools,
+//! like bless-chests, don't.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// Bumped when [`WorldSummary`] or the keys change, so old cache files get ignored
+const CACHE_VERSION: u32 = 2;
+
+/// What itemhunt needs to know about the chests of a world
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldSummary {
+    /// Number of stacks of every item id in the accessible chests
+    pub stacks: HashMap<i32, u16>,
+    /// Positions of the chests that were left out, because players can't reach them
+    pub inaccessible_chests: Vec<(u16, u16)>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct SummaryCache {
+    version: u32,
+    summaries: HashMap<String, WorldSummary>,
+}
+
+impl SummaryCache {
+    /// Load the cache, or start an empty one if the file doesn't exist or is from an
+    /// older version
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let cache: SummaryCache = serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid cache file {}: {}", path.display(), e))?;
+        if cache.version != CACHE_VERSION {
+            return Ok(Self::new());
+        }
+        Ok(cache)
+    }
+    fn new() -> Self {
+        SummaryCache {
+            version: CACHE_VERSION,
+            summaries: HashMap::new(),
+        }
+    }
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+    pub fn get(&self, key: &str) -> Option<&WorldSummary> {
+        self.summaries.get(key)
+    }
+    pub fn insert(&mut self, key: String, summary: WorldSummary) {
+        self.summaries.insert(key, summary);
+    }
+}
+
+/// The cache key of a world, `None` for worlds without a GUID
+pub fn cache_key(
+    header: &terraria_wld::Header,
+    base_header: &terraria_wld::BaseHeader,
+    metadata: &fs::Metadata,
+) -> Option<String> {
+    if header.guid == [0; 16] {
+        return None;
+    }
+    let world = format!("{}/{}", header.guid_string(), base_header.times_saved);
+    Some(with_file_state(&world, metadata))
+}
+
+/// Add the size and modification time of the world file to a key
+fn with_file_state(key: &str, metadata: &fs::Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |time| time.as_nanos());
+    format!("{}/{}/{}", key, metadata.len(), modified)
+}
+
+#[test]
+fn test_round_trip() {
+    let mut cache = SummaryCache::new();
+    let summary = WorldSummary {
+        stacks: HashMap::from([(54, 2), (50, 1)]),
+        inaccessible_chests: vec![(10, 20)],
+    };
+    cache.insert("key/3".to_owned(), summary.clone());
+    let text = serde_json::to_string(&cache).unwrap();
+    let loaded: SummaryCache = serde_json::from_str(&text).unwrap();
+    assert_eq!(loaded.get("key/3"), Some(&summary));
+    assert_eq!(loaded.get("key/4"), None);
+}
+
+#[test]
+fn test_rewritten_world() {
+    let path = std::env::temp_dir().join(format!("summary-cache-{}.wld", std::process::id()));
+    let key = |contents: &[u8], modified| {
+        let file = fs::File::create(&path).unwrap();
+        std::io::Write::write_all(&mut &file, contents).unwrap();
+        file.set_modified(UNIX_EPOCH + std::time::Duration::from_secs(modified))
+            .unwrap();
+        with_file_state("guid/3", &file.metadata().unwrap())
+    };
+    let original = key(b"chests", 1000);
+    assert_eq!(key(b"chests", 1000), original);
+    // Chests written back without the game bumping the save count
+    assert_ne!(key(b"more chests", 1000), original);
+    assert_ne!(key(b"CHESTS", 2000), original);
+    fs::remove_file(&path).unwrap();
+}
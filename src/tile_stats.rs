@@ -0,0 +1,269 @@
+//! Counting every tile, wall and liquid of a world, for `tile-stats`
+
+use crate::reports::{LayerCounts, OreAlternative, TileStat, TileStatsReport};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::str::FromStr;
+use terraria_strings::TileId;
+use terraria_wld::{objects::object_at, BaseHeader, Layer, Liquid};
+
+/// Groups of tiles the stats can be limited to
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Ores,
+    Gems,
+    Bars,
+    /// Tiles that don't store a frame, like dirt, stone and ores
+    Blocks,
+}
+
+impl FromStr for Category {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_lowercase()[..] {
+            "ore" | "ores" => Ok(Category::Ores),
+            "gem" | "gems" => Ok(Category::Gems),
+            "bar" | "bars" => Ok(Category::Bars),
+            "block" | "blocks" => Ok(Category::Blocks),
+            _ => Err(format!(
+                "Unknown category '{}', expected ores, gems, bars or blocks",
+                s
+            )),
+        }
+    }
+}
+
+impl Category {
+    fn contains(self, id: u16, base_header: &BaseHeader) -> bool {
+        match self {
+            Category::Ores => ORES.contains(&TileId(id)),
+            Category::Gems => GEMS.contains(&TileId(id)),
+            Category::Bars => TileId(id) == TileId::METAL_BARS,
+            Category::Blocks => !base_header.is_frame_important(id),
+        }
+    }
+}
+
+const ORES: &[TileId] = &[
+    TileId::COPPER,
+    TileId::TIN,
+    TileId::IRON,
+    TileId::LEAD,
+    TileId::SILVER,
+    TileId::TUNGSTEN,
+    TileId::GOLD,
+    TileId::PLATINUM,
+    TileId::DEMONITE,
+    TileId::CRIMTANE,
+    TileId::METEORITE,
+    TileId::HELLSTONE,
+    TileId::COBALT,
+    TileId::PALLADIUM,
+    TileId::MYTHRIL,
+    TileId::ORICHALCUM,
+    TileId::ADAMANTITE,
+    TileId::TITANIUM,
+    TileId::CHLOROPHYTE,
+    TileId::LUNAR_ORE,
+];
+
+const GEMS: &[TileId] = &[
+    TileId::AMETHYST,
+    TileId::TOPAZ,
+    TileId::SAPPHIRE,
+    TileId::EMERALD,
+    TileId::RUBY,
+    TileId::DIAMOND,
+    TileId::AMBER_STONE_BLOCK,
+    TileId::EXPOSED_GEMS,
+];
+
+/// World generation picks one ore of each pair, except in drunk worlds.
+/// The hardmode ones only show up after the Wall of Flesh is defeated.
+const ORE_ALTERNATIVES: &[(&str, TileId, TileId)] = &[
+    ("copper/tin", TileId::COPPER, TileId::TIN),
+    ("iron/lead", TileId::IRON, TileId::LEAD),
+    ("silver/tungsten", TileId::SILVER, TileId::TUNGSTEN),
+    ("gold/platinum", TileId::GOLD, TileId::PLATINUM),
+    ("demonite/crimtane", TileId::DEMONITE, TileId::CRIMTANE),
+    ("cobalt/palladium", TileId::COBALT, TileId::PALLADIUM),
+    ("mythril/orichalcum", TileId::MYTHRIL, TileId::ORICHALCUM),
+    ("adamantite/titanium", TileId::ADAMANTITE, TileId::TITANIUM),
+];
+
+/// Styles of the exposed gem tile
+const EXPOSED_GEM_NAMES: &[&str] = &[
+    "Amethyst", "Topaz", "Sapphire", "Emerald", "Ruby", "Diamond", "Amber",
+];
+
+/// Styles of the placed bar tile
+const BAR_NAMES: &[&str] = &[
+    "Copper",
+    "Tin",
+    "Iron",
+    "Lead",
+    "Silver",
+    "Tungsten",
+    "Gold",
+    "Platinum",
+    "Demonite",
+    "Meteorite",
+    "Hellstone",
+    "Cobalt",
+    "Palladium",
+    "Mythril",
+    "Orichalcum",
+    "Adamantite",
+    "Titanium",
+    "Chlorophyte",
+    "Hallowed",
+    "Crimtane",
+    "Shroomite",
+    "Spectre",
+    "Luminite",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Key {
+    /// Tiles whose style matters get counted per style
+    Tile(u16, Option<u16>),
+    Wall(u16),
+    Liquid(Liquid),
+}
+
+/// Count everything in the world. If `categories` isn't empty, only tiles in any of them
+/// are listed. The ore alternatives are always included.
+pub fn tile_stats(
+    path: &Path,
+    categories: &[Category],
+    show_layers: bool,
+) -> Result<TileStatsReport, Box<dyn Error>> {
+    let (file, base_header) = terraria_wld::open(path, false)?;
+    let header = terraria_wld::read_header(&file, base_header.offsets.header as u64)?;
+    let mut counts: HashMap<Key, [u32; 4]> = HashMap::new();
+    terraria_wld::read_tiles(&file, &base_header, |tile, x, y| {
+        let layer = header.layer(y) as usize;
+        if let Some(id) = tile.front {
+            let style = match TileId(id) {
+                TileId::EXPOSED_GEMS | TileId::METAL_BARS => {
+                    object_at(&tile, x, y).map(|object| object.style)
+                }
+                _ => None,
+            };
+            counts.entry(Key::Tile(id, style)).or_default()[layer] += 1;
+        }
+        if let Some(id) = tile.back {
+            counts.entry(Key::Wall(id)).or_default()[layer] += 1;
+        }
+        if let Some(liquid) = tile.liquid {
+            counts.entry(Key::Liquid(liquid)).or_default()[layer] += 1;
+        }
+    })?;
+    let tile_total = |id| -> u32 {
+        counts
+            .iter()
+            .filter(|(key, _)| matches!(key, Key::Tile(tile_id, _) if *tile_id == id))
+            .map(|(_, layers)| layers.iter().sum::<u32>())
+            .sum()
+    };
+    let tile_name = |id| match terraria_strings::tile_ids().name_by_id(id) {
+        Some(name) => name.to_owned(),
+        None => format!("Tile {}", id),
+    };
+    let ore_alternatives = ORE_ALTERNATIVES
+        .iter()
+        .map(|&(ores, a, b)| OreAlternative {
+            ores,
+            generated: [a.0, b.0]
+                .into_iter()
+                .filter(|&id| tile_total(id) > 0)
+                .map(tile_name)
+                .collect(),
+        })
+        .collect();
+    let mut stats: Vec<TileStat> = counts
+        .iter()
+        .filter(|(key, _)| match key {
+            Key::Tile(id, _) => {
+                categories.is_empty() || categories.iter().any(|c| c.contains(*id, &base_header))
+            }
+            Key::Wall(_) | Key::Liquid(_) => categories.is_empty(),
+        })
+        .map(|(&key, layers)| {
+            let (kind, id, style, name) = match key {
+                Key::Tile(id, style) => ("tile", Some(id), style, styled_name(id, style)),
+                Key::Wall(id) => (
+                    "wall",
+                    Some(id),
+                    None,
+                    match terraria_strings::wall_ids().name_by_id(id) {
+                        Some(name) => name.to_owned(),
+                        None => format!("Wall {}", id),
+                    },
+                ),
+                Key::Liquid(liquid) => ("liquid", None, None, liquid.name().to_owned()),
+            };
+            TileStat {
+                kind,
+                id,
+                style,
+                name,
+                total: layers.iter().sum(),
+                layers: LayerCounts {
+                    surface: layers[Layer::Surface as usize],
+                    underground: layers[Layer::Underground as usize],
+                    cavern: layers[Layer::Cavern as usize],
+                    underworld: layers[Layer::Underworld as usize],
+                },
+            }
+        })
+        .collect();
+    let kind_order = |kind| match kind {
+        "tile" => 0,
+        "wall" => 1,
+        _ => 2,
+    };
+    stats.sort_by(|a, b| {
+        kind_order(a.kind)
+            .cmp(&kind_order(b.kind))
+            .then(b.total.cmp(&a.total))
+            .then(a.name.cmp(&b.name))
+    });
+    Ok(TileStatsReport {
+        world: path.display().to_string(),
+        show_layers,
+        stats,
+        ore_alternatives,
+    })
+}
+
+/// Name of a tile, including the style for tiles that are counted per style,
+/// like "Gold Bar"
+fn styled_name(id: u16, style: Option<u16>) -> String {
+    let base = match terraria_strings::tile_ids().name_by_id(id) {
+        Some(name) => name,
+        None => return format!("Tile {}", id),
+    };
+    let style_names = match TileId(id) {
+        TileId::EXPOSED_GEMS => EXPOSED_GEM_NAMES,
+        TileId::METAL_BARS => BAR_NAMES,
+        _ => return base.to_owned(),
+    };
+    match style.and_then(|style| style_names.get(usize::from(style))) {
+        Some(style_name) => format!("{} {}", style_name, base),
+        None => format!("{} (style {})", base, style.unwrap_or_default()),
+    }
+}
+
+#[test]
+fn test_styled_name() {
+    assert_eq!(styled_name(TileId::METAL_BARS.0, Some(6)), "Gold Bar");
+    assert_eq!(styled_name(TileId::EXPOSED_GEMS.0, Some(0)), "Amethyst Gem");
+    assert_eq!(
+        styled_name(TileId::METAL_BARS.0, Some(99)),
+        "Bar (style 99)"
+    );
+    assert_eq!(styled_name(TileId::COPPER.0, None), "Copper Ore");
+}
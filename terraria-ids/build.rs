@@ -0,0 +1,70 @@
+//! Generates id constants (`ItemId::HERMES_BOOTS`, `TileId::CRIMSTONE`, etc.)
+//! from the id lists. The id types themselves are defined in the crate.
+
+use std::collections::HashSet;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    let mut code = String::new();
+    for (type_name, list_path) in [
+        ("ItemId", "item_id_list.txt"),
+        ("TileId", "tile_id_list.txt"),
+        ("WallId", "wall_id_list.txt"),
+    ] {
+        println!("cargo:rerun-if-changed={}", list_path);
+        let list = fs::read_to_string(list_path).unwrap();
+        gen_consts(&mut code, type_name, &list);
+    }
+    let out_path = Path::new(&env::var("OUT_DIR").unwrap()).join("ids.rs");
+    fs::write(out_path, code).unwrap();
+}
+
+fn gen_consts(code: &mut String, type_name: &str, list: &str) {
+    let mut used_names = HashSet::new();
+    writeln!(code, "impl {} {{", type_name).unwrap();
+    for line in list.lines() {
+        let mut parts = line.split('\t');
+        let id: u16 = parts.next().unwrap().parse().unwrap();
+        let name = parts.next().unwrap();
+        let const_name = screaming_snake_case(parts.next().unwrap());
+        if !used_names.insert(const_name.clone()) {
+            panic!("Duplicate {} constant: {}", type_name, const_name);
+        }
+        writeln!(code, "    /// {}", name).unwrap();
+        writeln!(
+            code,
+            "    pub const {}: {} = {}({});",
+            const_name, type_name, type_name, id
+        )
+        .unwrap();
+    }
+    writeln!(code, "}}").unwrap();
+}
+
+/// Converts internal names like `IronPickaxe` or `LogicGate_AND` to `IRON_PICKAXE`
+/// and `LOGIC_GATE_AND`.
+fn screaming_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' {
+            out.push('_');
+            continue;
+        }
+        if i > 0 && c.is_uppercase() {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+            if prev.is_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_uppercase() && next_is_lower)
+            {
+                out.push('_');
+            }
+        }
+        out.push(c.to_ascii_uppercase());
+    }
+    out
+}
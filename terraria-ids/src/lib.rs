@@ -0,0 +1,62 @@
+//! Item, tile and wall ids
+//!
+//! The id lists are the source of truth for everything that needs ids. Constants like
+//! `ItemId::HERMES_BOOTS` are generated from them at build time, and `terraria-strings`
+//! uses them for name lookups.
+
+/// Defines a newtype for ids of one kind, so item, tile and wall ids can't be mixed up
+macro_rules! id_type {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(pub u16);
+
+        impl From<$name> for u16 {
+            fn from(id: $name) -> u16 {
+                id.0
+            }
+        }
+
+        impl From<u16> for $name {
+            fn from(id: u16) -> $name {
+                $name(id)
+            }
+        }
+
+        impl From<$name> for i32 {
+            fn from(id: $name) -> i32 {
+                i32::from(id.0)
+            }
+        }
+    };
+}
+
+id_type!(
+    /// An item id. Constants like `ItemId::HERMES_BOOTS` are generated from the item id
+    /// list at build time.
+    ItemId
+);
+id_type!(
+    /// A tile id. Constants like `TileId::CRIMSTONE` are generated from the tile id list
+    /// at build time.
+    TileId
+);
+id_type!(
+    /// A wall id. Constants like `WallId::LIHZAHRD_BRICK_UNSAFE` are generated from the
+    /// wall id list at build time.
+    WallId
+);
+
+include!(concat!(env!("OUT_DIR"), "/ids.rs"));
+
+/// These are taken from https://terraria.fandom.com/wiki/Item_IDs
+pub static ITEM_ID_LIST: &str = include_str!("../item_id_list.txt");
+pub static TILE_ID_LIST: &str = include_str!("../tile_id_list.txt");
+pub static WALL_ID_LIST: &str = include_str!("../wall_id_list.txt");
+
+#[test]
+fn test_consts() {
+    assert_eq!(ItemId::IRON_PICKAXE, ItemId(1));
+    assert_eq!(u16::from(TileId::CRIMSTONE), 203);
+    assert_eq!(WallId::LIHZAHRD_BRICK_UNSAFE.0, 87);
+}
@@ -0,0 +1,351 @@
+//! Reading Terraria player map (.map) files
+//!
+//! Every player keeps a map file per world they visited, in a directory named after
+//! the player file (`Players/Foo.plr` has its maps in `Players/Foo/`).
+//! The map remembers which parts of the world the player has explored, which is what
+//! this crate is mostly interested in.
+
+use byteorder::{ReadBytesExt, LE};
+use flate2::read::DeflateDecoder;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use terraria_wld::Header;
+
+/// Oldest release with the "relogic" file metadata. Older maps are not supported.
+const MIN_SUPPORTED_RELEASE: i32 = 135;
+const MAGIC: &[u8; 7] = b"relogic";
+const FILE_TYPE_MAP: u8 = 1;
+
+pub struct Map {
+    pub release: i32,
+    pub world_name: String,
+    pub world_id: i32,
+    pub width: u16,
+    pub height: u16,
+    /// (tile id, option) for each tile type index used in the file
+    tile_lookup: Vec<(u16, u8)>,
+    /// (wall id, option) for each wall type index used in the file
+    wall_lookup: Vec<(u16, u8)>,
+    /// Row major, `width * height` cells
+    cells: Vec<Cell>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Cell {
+    group: u8,
+    light: u8,
+    index: u16,
+}
+
+/// The game draws cells this dark or darker as unexplored
+const MAX_UNEXPLORED_LIGHT: u8 = 18;
+
+impl Cell {
+    fn explored(&self) -> bool {
+        self.group != GROUP_EMPTY && self.light > MAX_UNEXPLORED_LIGHT
+    }
+}
+
+/// What the player sees on the map at a position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapTile {
+    Unexplored,
+    /// The option differentiates the map colors of some tiles, like the different
+    /// kinds of chests
+    Tile {
+        id: u16,
+        option: u8,
+    },
+    Wall {
+        id: u16,
+        option: u8,
+    },
+    Water,
+    Lava,
+    Honey,
+    Sky,
+    /// Dirt or rock background
+    Background,
+}
+
+const GROUP_EMPTY: u8 = 0;
+const GROUP_TILE: u8 = 1;
+const GROUP_WALL: u8 = 2;
+const GROUP_WATER: u8 = 3;
+const GROUP_LAVA: u8 = 4;
+const GROUP_HONEY: u8 = 5;
+const GROUP_SKY: u8 = 6;
+const GROUP_BACKGROUND: u8 = 7;
+
+impl Map {
+    /// Whether the player has explored the given position
+    pub fn explored(&self, x: u16, y: u16) -> bool {
+        match self.cell(x, y) {
+            Some(cell) => cell.explored(),
+            None => false,
+        }
+    }
+    /// How lit up the position is on the map, 18 or less being unexplored and 255 fully lit
+    pub fn light(&self, x: u16, y: u16) -> u8 {
+        self.cell(x, y).map_or(0, |cell| cell.light)
+    }
+    pub fn tile(&self, x: u16, y: u16) -> MapTile {
+        let cell = match self.cell(x, y) {
+            Some(cell) if cell.light > MAX_UNEXPLORED_LIGHT => cell,
+            _ => return MapTile::Unexplored,
+        };
+        let lookup = |table: &[(u16, u8)]| table.get(usize::from(cell.index)).copied();
+        match cell.group {
+            GROUP_TILE => match lookup(&self.tile_lookup) {
+                Some((id, option)) => MapTile::Tile { id, option },
+                None => MapTile::Unexplored,
+            },
+            GROUP_WALL => match lookup(&self.wall_lookup) {
+                Some((id, option)) => MapTile::Wall { id, option },
+                None => MapTile::Unexplored,
+            },
+            GROUP_WATER => MapTile::Water,
+            GROUP_LAVA => MapTile::Lava,
+            GROUP_HONEY => MapTile::Honey,
+            GROUP_SKY => MapTile::Sky,
+            GROUP_BACKGROUND => MapTile::Background,
+            _ => MapTile::Unexplored,
+        }
+    }
+    /// Fraction of the world the player has explored, from 0.0 to 1.0
+    pub fn explored_fraction(&self) -> f64 {
+        let explored = self.cells.iter().filter(|cell| cell.explored()).count();
+        explored as f64 / self.cells.len().max(1) as f64
+    }
+    /// Whether this map belongs to the world with the given header
+    pub fn matches(&self, header: &Header) -> bool {
+        self.world_id == header.id && self.width == header.width && self.height == header.height
+    }
+    fn cell(&self, x: u16, y: u16) -> Option<&Cell> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.cells
+            .get(usize::from(y) * usize::from(self.width) + usize::from(x))
+    }
+}
+
+/// The file name the game uses for the map of a world, like `<guid>.map`
+pub fn map_file_name(header: &Header) -> String {
+    format!("{}.map", header.guid_string())
+}
+
+/// The path of the map a player has of a world, given the path of their .plr file
+pub fn player_map_path(player_path: &Path, header: &Header) -> PathBuf {
+    player_path.with_extension("").join(map_file_name(header))
+}
+
+pub fn read_map(path: &Path) -> Result<Map, Box<dyn Error>> {
+    let mut f = BufReader::new(File::open(path)?);
+    let release = f.read_i32::<LE>()?;
+    if release < MIN_SUPPORTED_RELEASE {
+        return Err(format!("Unsupported map version: {}", release).into());
+    }
+    let mut magic = [0; 7];
+    f.read_exact(&mut magic)?;
+    let file_type = f.read_u8()?;
+    if &magic != MAGIC || file_type != FILE_TYPE_MAP {
+        return Err("Not a Terraria map file".into());
+    }
+    let _revision = f.read_u32::<LE>()?;
+    let _favorite = f.read_u64::<LE>()?;
+    let world_name = read_string(&mut f)?;
+    let world_id = f.read_i32::<LE>()?;
+    let height = f.read_i32::<LE>()?;
+    let width = f.read_i32::<LE>()?;
+    let n_tiles = f.read_i16::<LE>()?;
+    let n_walls = f.read_i16::<LE>()?;
+    let _n_liquids = f.read_i16::<LE>()?;
+    let _n_sky = f.read_i16::<LE>()?;
+    let _n_dirt = f.read_i16::<LE>()?;
+    let _n_rock = f.read_i16::<LE>()?;
+    let tile_has_options = read_bits(&mut f, n_tiles as usize)?;
+    let wall_has_options = read_bits(&mut f, n_walls as usize)?;
+    let tile_lookup = read_lookup(&mut f, &tile_has_options)?;
+    let wall_lookup = read_lookup(&mut f, &wall_has_options)?;
+    let (width, height) = (
+        u16::try_from(width).map_err(|_| "Invalid map width")?,
+        u16::try_from(height).map_err(|_| "Invalid map height")?,
+    );
+    let cells = read_cells(&mut DeflateDecoder::new(f), width, height)?;
+    Ok(Map {
+        release,
+        world_name,
+        world_id,
+        width,
+        height,
+        tile_lookup,
+        wall_lookup,
+        cells,
+    })
+}
+
+fn read_cells(f: &mut impl Read, width: u16, height: u16) -> io::Result<Vec<Cell>> {
+    let w = usize::from(width);
+    let mut cells = vec![Cell::default(); w * usize::from(height)];
+    for y in 0..usize::from(height) {
+        let row = &mut cells[y * w..(y + 1) * w];
+        let mut x = 0;
+        while x < w {
+            let flags1 = f.read_u8()?;
+            let _flags2 = if flags1 & 1 != 0 { f.read_u8()? } else { 0 };
+            let group = (flags1 >> 1) & 0b111;
+            let index = match group {
+                GROUP_TILE | GROUP_WALL | GROUP_BACKGROUND => {
+                    if flags1 & 0x10 != 0 {
+                        f.read_u16::<LE>()?
+                    } else {
+                        u16::from(f.read_u8()?)
+                    }
+                }
+                _ => 0,
+            };
+            let light = if flags1 & 0x20 != 0 {
+                f.read_u8()?
+            } else {
+                255
+            };
+            let repeat = match flags1 >> 6 {
+                1 => usize::from(f.read_u8()?),
+                2 => f.read_i16::<LE>()? as usize,
+                _ => 0,
+            };
+            if group == GROUP_EMPTY {
+                x += repeat + 1;
+                continue;
+            }
+            let mut cell = Cell {
+                group,
+                light,
+                index,
+            };
+            // Fully lit runs share the light value, otherwise every repeat has its own
+            let shared_light = light == 255;
+            for i in 0..=repeat {
+                if i > 0 && !shared_light {
+                    cell.light = f.read_u8()?;
+                }
+                if let Some(slot) = row.get_mut(x + i) {
+                    *slot = cell;
+                }
+            }
+            x += repeat + 1;
+        }
+    }
+    Ok(cells)
+}
+
+fn read_bits(f: &mut impl Read, count: usize) -> io::Result<Vec<bool>> {
+    let mut bits = Vec::with_capacity(count);
+    let mut byte = 0;
+    for i in 0..count {
+        if i % 8 == 0 {
+            byte = f.read_u8()?;
+        }
+        bits.push(byte & (1 << (i % 8)) != 0);
+    }
+    Ok(bits)
+}
+
+/// Reads the option counts of types that have options, and expands them into
+/// a table of (id, option) for each type index.
+fn read_lookup(f: &mut impl Read, has_options: &[bool]) -> io::Result<Vec<(u16, u8)>> {
+    let mut counts = Vec::with_capacity(has_options.len());
+    for &has in has_options {
+        counts.push(if has { f.read_u8()? } else { 1 });
+    }
+    Ok(expand_lookup(&counts))
+}
+
+fn expand_lookup(option_counts: &[u8]) -> Vec<(u16, u8)> {
+    option_counts
+        .iter()
+        .enumerate()
+        .flat_map(|(id, &count)| (0..count).map(move |option| (id as u16, option)))
+        .collect()
+}
+
+fn read_string(f: &mut impl Read) -> io::Result<String> {
+    let mut len = 0;
+    let mut shift: u32 = 0;
+    loop {
+        let segment = f.read_u8()?;
+        len |= usize::from(segment & 0b0111_1111) << shift;
+        shift += 7;
+        if (segment & 0b1000_0000) == 0 {
+            break;
+        }
+    }
+    let mut buf = vec![0u8; len];
+    f.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+#[test]
+fn test_read_cells() {
+    // A 4x2 map: a run of 3 dirt tiles with a stone wall after it, then an unexplored row
+    let data = [
+        // tile, u8 type, u8 repeat of 2, fully lit
+        0b0100_0010,
+        0,
+        2,
+        // wall, u8 type, own light value
+        0b0010_0100,
+        0,
+        100,
+        // unexplored, u8 repeat of 3
+        0b0100_0000,
+        3,
+    ];
+    let cells = read_cells(&mut &data[..], 4, 2).unwrap();
+    let map = Map {
+        release: 279,
+        world_name: String::new(),
+        world_id: 0,
+        width: 4,
+        height: 2,
+        tile_lookup: expand_lookup(&[1, 2]),
+        wall_lookup: expand_lookup(&[0, 1]),
+        cells,
+    };
+    assert_eq!(map.tile(2, 0), MapTile::Tile { id: 0, option: 0 });
+    assert_eq!(map.tile(3, 0), MapTile::Wall { id: 1, option: 0 });
+    assert_eq!(map.light(3, 0), 100);
+    assert!(!map.explored(0, 1));
+    assert_eq!(map.explored_fraction(), 0.5);
+}
+
+#[test]
+fn test_dim_cells_unexplored() {
+    let cell = |light| Cell {
+        group: GROUP_TILE,
+        light,
+        index: 0,
+    };
+    let map = Map {
+        release: 279,
+        world_name: String::new(),
+        world_id: 0,
+        width: 2,
+        height: 1,
+        tile_lookup: expand_lookup(&[1]),
+        wall_lookup: Vec::new(),
+        cells: vec![cell(18), cell(19)],
+    };
+    assert!(!map.explored(0, 0));
+    assert_eq!(map.tile(0, 0), MapTile::Unexplored);
+    assert!(map.explored(1, 0));
+    assert_eq!(map.explored_fraction(), 0.5);
+}
+
+#[test]
+fn test_expand_lookup() {
+    assert_eq!(expand_lookup(&[1, 0, 2]), [(0, 0), (2, 0), (2, 1)]);
+}
@@ -0,0 +1,343 @@
+//! Reader for Terraria player (.plr) files
+//!
+//! Player files are AES encrypted. After decryption, the layout is similar to .wld files:
+//! a version number and a "relogic" magic header, followed by the player data.
+//!
+//! Only player files from Terraria 1.4 and later are supported.
+
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
+use byteorder::{ReadBytesExt, LE};
+use std::error::Error;
+use std::io::{self, prelude::*, Cursor};
+use std::path::Path;
+use terraria_wld::Item;
+
+/// The key (and iv) Terraria encrypts player files with. It's "h3y_gUyZ" in UTF-16.
+const KEY: &[u8; 16] = b"h\x003\x00y\x00_\x00g\x00U\x00y\x00Z\x00";
+
+/// The oldest release (file format version) we can read. This is Terraria 1.4.0.5.
+const MIN_SUPPORTED_RELEASE: i32 = 230;
+
+const ARMOR_SLOTS: usize = 20;
+const DYE_SLOTS: usize = 10;
+const INVENTORY_SLOTS: usize = 58;
+const MISC_SLOTS: usize = 5;
+const BANK_SLOTS: usize = 40;
+const MAX_SPAWN_POINTS: usize = 200;
+const HIDE_INFO_SLOTS: usize = 13;
+const DPAD_BINDINGS: usize = 4;
+
+pub struct Player {
+    pub version: i32,
+    pub name: String,
+    pub difficulty: Difficulty,
+    /// Play time in .NET ticks (100 nanoseconds)
+    pub play_time: i64,
+    pub life: i32,
+    pub max_life: i32,
+    pub mana: i32,
+    pub max_mana: i32,
+    /// Whether the Demon Heart was consumed
+    pub extra_accessory: bool,
+    /// Armor, accessory, and vanity slots.
+    ///
+    /// 0-2 are armor, 3-9 are accessories, 10-12 are vanity armor,
+    /// 13-19 are vanity accessories.
+    pub armor: Vec<Item>,
+    pub dyes: Vec<Item>,
+    pub inventory: Vec<InventoryItem>,
+    /// Pet, light pet, minecart, mount and hook slots
+    pub misc_equips: Vec<Item>,
+    pub misc_dyes: Vec<Item>,
+    pub piggy_bank: Vec<Item>,
+    pub safe: Vec<Item>,
+    pub defenders_forge: Vec<Item>,
+    pub void_vault: Vec<Item>,
+    pub buffs: Vec<Buff>,
+    pub spawn_points: Vec<SpawnPoint>,
+    pub angler_quests_finished: i32,
+    /// Journey mode research progress.
+    ///
+    /// Pairs of (internal item name, number of items sacrificed).
+    pub research: Vec<(String, i32)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Difficulty {
+    Classic,
+    Mediumcore,
+    Hardcore,
+    Journey,
+    Unknown(u8),
+}
+
+impl Difficulty {
+    fn from_byte(byte: u8) -> Self {
+        match byte {
+            0 => Difficulty::Classic,
+            1 => Difficulty::Mediumcore,
+            2 => Difficulty::Hardcore,
+            3 => Difficulty::Journey,
+            etc => Difficulty::Unknown(etc),
+        }
+    }
+}
+
+#[derive(Default, Copy, Clone)]
+pub struct InventoryItem {
+    pub item: Item,
+    pub favorited: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Buff {
+    pub id: i32,
+    /// Remaining time in ticks
+    pub time: i32,
+}
+
+/// A bed spawn point the player has set in a world
+#[derive(Debug, Clone)]
+pub struct SpawnPoint {
+    pub x: i32,
+    pub y: i32,
+    /// Matches `Header::id` of the world
+    pub world_id: i32,
+    pub world_name: String,
+}
+
+pub fn read_player(path: &Path) -> Result<Player, Box<dyn Error>> {
+    let data = std::fs::read(path)?;
+    let decrypted = decrypt(&data)?;
+    parse(&decrypted)
+}
+
+/// Decrypt the raw contents of a .plr file
+pub fn decrypt(data: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut buf = data.to_vec();
+    let len = cbc::Decryptor::<aes::Aes128>::new(KEY.into(), KEY.into())
+        .decrypt_padded_mut::<Pkcs7>(&mut buf)
+        .map_err(|_| "Failed to decrypt player file")?
+        .len();
+    buf.truncate(len);
+    Ok(buf)
+}
+
+/// Parse decrypted player data
+pub fn parse(data: &[u8]) -> Result<Player, Box<dyn Error>> {
+    let mut f = Cursor::new(data);
+    let version = f.read_i32::<LE>()?;
+    if version < MIN_SUPPORTED_RELEASE {
+        return Err(format!("Unsupported player file version: {}", version).into());
+    }
+    let mut magic = [0u8; 7];
+    f.read_exact(&mut magic)?;
+    if magic[..] != b"relogic"[..] {
+        return Err("Not a valid terraria player file.".into());
+    }
+    let filetype = f.read_u8()?;
+    if filetype != 3 {
+        return Err(format!("Unsupported filetype: {}", filetype).into());
+    }
+    let _revision = f.read_u32::<LE>()?;
+    let _is_favorite = f.read_u64::<LE>()?;
+    let name = read_string(&mut f)?;
+    let difficulty = Difficulty::from_byte(f.read_u8()?);
+    let play_time = f.read_i64::<LE>()?;
+    let _hair = f.read_i32::<LE>()?;
+    let _hair_dye = f.read_u8()?;
+    let _hide_visible_accessory_1 = f.read_u8()?;
+    let _hide_visible_accessory_2 = f.read_u8()?;
+    let _hide_misc = f.read_u8()?;
+    let _skin_variant = f.read_u8()?;
+    let life = f.read_i32::<LE>()?;
+    let max_life = f.read_i32::<LE>()?;
+    let mana = f.read_i32::<LE>()?;
+    let max_mana = f.read_i32::<LE>()?;
+    let extra_accessory = read_bool(&mut f)?;
+    let _unlocked_biome_torches = read_bool(&mut f)?;
+    let _using_biome_torches = read_bool(&mut f)?;
+    if version >= 256 {
+        let _ate_artisan_bread = read_bool(&mut f)?;
+    }
+    if version >= 260 {
+        // Aegis Crystal, Aegis Fruit, Arcane Crystal, Galaxy Pearl, Gummy Worm, Ambrosia
+        for _ in 0..6 {
+            let _used = read_bool(&mut f)?;
+        }
+    }
+    let _downed_dd2_event_any_difficulty = read_bool(&mut f)?;
+    let _tax_money = f.read_i32::<LE>()?;
+    if version >= 254 {
+        let _deaths_pve = f.read_i32::<LE>()?;
+        let _deaths_pvp = f.read_i32::<LE>()?;
+    }
+    // Hair, skin, eye, shirt, undershirt, pants and shoe colors
+    let mut _colors = [0u8; 7 * 3];
+    f.read_exact(&mut _colors)?;
+    let armor = read_items(&mut f, ARMOR_SLOTS, false)?;
+    let dyes = read_items(&mut f, DYE_SLOTS, false)?;
+    let mut inventory = Vec::with_capacity(INVENTORY_SLOTS);
+    for _ in 0..INVENTORY_SLOTS {
+        let id = f.read_i32::<LE>()?;
+        let stack = f.read_i32::<LE>()?;
+        let prefix_id = f.read_u8()?;
+        let favorited = read_bool(&mut f)?;
+        inventory.push(InventoryItem {
+            item: make_item(id, stack, prefix_id),
+            favorited,
+        });
+    }
+    // Misc equips and their dyes are interleaved
+    let mut misc_equips = Vec::with_capacity(MISC_SLOTS);
+    let mut misc_dyes = Vec::with_capacity(MISC_SLOTS);
+    for _ in 0..MISC_SLOTS {
+        misc_equips.extend(read_items(&mut f, 1, false)?);
+        misc_dyes.extend(read_items(&mut f, 1, false)?);
+    }
+    let piggy_bank = read_items(&mut f, BANK_SLOTS, true)?;
+    let safe = read_items(&mut f, BANK_SLOTS, true)?;
+    let defenders_forge = read_items(&mut f, BANK_SLOTS, true)?;
+    let mut void_vault = Vec::with_capacity(BANK_SLOTS);
+    for _ in 0..BANK_SLOTS {
+        void_vault.extend(read_items(&mut f, 1, true)?);
+        if version >= 255 {
+            let _favorited = read_bool(&mut f)?;
+        }
+    }
+    let _void_vault_info = f.read_u8()?;
+    let n_buffs = if version >= 252 { 44 } else { 22 };
+    let mut buffs = Vec::new();
+    for _ in 0..n_buffs {
+        let id = f.read_i32::<LE>()?;
+        let time = f.read_i32::<LE>()?;
+        if id != 0 {
+            buffs.push(Buff { id, time });
+        }
+    }
+    let mut spawn_points = Vec::new();
+    for _ in 0..MAX_SPAWN_POINTS {
+        let x = f.read_i32::<LE>()?;
+        if x == -1 {
+            break;
+        }
+        spawn_points.push(SpawnPoint {
+            x,
+            y: f.read_i32::<LE>()?,
+            world_id: f.read_i32::<LE>()?,
+            world_name: read_string(&mut f)?,
+        });
+    }
+    let _hotbar_locked = read_bool(&mut f)?;
+    for _ in 0..HIDE_INFO_SLOTS {
+        let _hide_info = read_bool(&mut f)?;
+    }
+    let angler_quests_finished = f.read_i32::<LE>()?;
+    for _ in 0..DPAD_BINDINGS {
+        let _dpad_binding = f.read_i32::<LE>()?;
+    }
+    let n_builder_acc_status = if version >= 249 { 12 } else { 10 };
+    for _ in 0..n_builder_acc_status {
+        let _builder_acc_status = f.read_i32::<LE>()?;
+    }
+    let _bartender_quest_log = f.read_i32::<LE>()?;
+    if read_bool(&mut f)? {
+        let _respawn_timer = f.read_i32::<LE>()?;
+    }
+    let _last_time_saved = f.read_i64::<LE>()?;
+    let _golfer_score_accumulated = f.read_i32::<LE>()?;
+    let n_research = f.read_i32::<LE>()?;
+    let mut research = Vec::new();
+    for _ in 0..n_research {
+        let internal_name = read_string(&mut f)?;
+        let count = f.read_i32::<LE>()?;
+        research.push((internal_name, count));
+    }
+    Ok(Player {
+        version,
+        name,
+        difficulty,
+        play_time,
+        life,
+        max_life,
+        mana,
+        max_mana,
+        extra_accessory,
+        armor,
+        dyes,
+        inventory,
+        misc_equips,
+        misc_dyes,
+        piggy_bank,
+        safe,
+        defenders_forge,
+        void_vault,
+        buffs,
+        spawn_points,
+        angler_quests_finished,
+        research,
+    })
+}
+
+/// Read `n` items. Equipment slots don't store stack sizes, they always have 1 item.
+fn read_items(f: &mut impl Read, n: usize, with_stack: bool) -> io::Result<Vec<Item>> {
+    let mut items = Vec::with_capacity(n);
+    for _ in 0..n {
+        let id = f.read_i32::<LE>()?;
+        let stack = if with_stack { f.read_i32::<LE>()? } else { 1 };
+        let prefix_id = f.read_u8()?;
+        items.push(make_item(id, stack, prefix_id));
+    }
+    Ok(items)
+}
+
+fn make_item(id: i32, stack: i32, prefix_id: u8) -> Item {
+    if id == 0 {
+        Item::default()
+    } else {
+        Item {
+            stack: stack as u16,
+            id,
+            prefix_id,
+        }
+    }
+}
+
+fn read_bool(f: &mut impl Read) -> io::Result<bool> {
+    Ok(f.read_u8()? != 0)
+}
+
+fn read_string(f: &mut impl Read) -> io::Result<String> {
+    let mut len = 0;
+    let mut shift: u32 = 0;
+    loop {
+        let segment = f.read_u8()?;
+        len |= usize::from(segment & 0b0111_1111) << shift;
+        shift += 7;
+        if (segment & 0b1000_0000) == 0 {
+            break;
+        }
+    }
+    let mut buf = vec![0u8; len];
+    f.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+#[test]
+fn test_decrypt() {
+    use aes::cipher::BlockEncryptMut;
+    let plain = b"relogic player data";
+    let mut buf = [0u8; 32];
+    buf[..plain.len()].copy_from_slice(plain);
+    let encrypted = cbc::Encryptor::<aes::Aes128>::new(KEY.into(), KEY.into())
+        .encrypt_padded_mut::<Pkcs7>(&mut buf, plain.len())
+        .unwrap();
+    assert_eq!(decrypt(encrypted).unwrap(), plain);
+}
+
+#[test]
+fn test_read_string() {
+    let mut data = vec![130, 1];
+    data.extend([b'a'; 130]);
+    assert_eq!(read_string(&mut Cursor::new(data)).unwrap().len(), 130);
+}
@@ -0,0 +1,210 @@
+//! Rendering worlds to images, without needing a window
+//!
+//! Every tile becomes one pixel, colored by the first of its block, liquid and wall that
+//! is present and enabled in [`Layers`]. Wires are drawn over everything else.
+//! The colors come from `map_colors.txt`, see [`palette`].
+
+pub mod palette;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use terraria_wld::{read_tiles, BaseHeader, Tile};
+
+pub type Rgba = [u8; 4];
+
+/// Images bigger than this take gigabytes of memory, so scaling up to them is refused
+pub const MAX_PIXELS: f64 = 256.0 * 1024.0 * 1024.0;
+
+/// The size of a `width` x `height` image scaled by `scale`, or an error if it would have
+/// more than [`MAX_PIXELS`] pixels
+pub fn scaled_size(width: u32, height: u32, scale: f64) -> Result<(u32, u32), String> {
+    let scaled_width = (f64::from(width) * scale).round().max(1.0);
+    let scaled_height = (f64::from(height) * scale).round().max(1.0);
+    if scaled_width * scaled_height > MAX_PIXELS {
+        return Err(format!(
+            "Scaling {}x{} by {} would make an image of {}x{} pixels, which is too big",
+            width, height, scale, scaled_width, scaled_height
+        ));
+    }
+    Ok((scaled_width as u32, scaled_height as u32))
+}
+
+/// Which parts of tiles to draw
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layers {
+    pub tiles: bool,
+    pub walls: bool,
+    pub liquids: bool,
+    pub wires: bool,
+}
+
+impl Default for Layers {
+    fn default() -> Self {
+        Layers {
+            tiles: true,
+            walls: true,
+            liquids: true,
+            wires: false,
+        }
+    }
+}
+
+/// A rectangle of the world in tiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Region {
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        x >= self.x && y >= self.y && x - self.x < self.width && y - self.y < self.height
+    }
+}
+
+/// The color of a tile, `None` if there's nothing to draw. Like on the game's map, a
+/// block without a map color shows the liquid or wall behind it.
+pub fn tile_pixel(tile: &Tile, layers: &Layers) -> Option<Rgba> {
+    if layers.wires {
+        if let Some(color) = palette::wire_color(tile.wires) {
+            return Some(color);
+        }
+    }
+    if layers.tiles {
+        if let Some(color) = palette::block_color(tile) {
+            return Some(color);
+        }
+    }
+    match tile.liquid {
+        Some(liquid) if layers.liquids => Some(palette::liquid_color(liquid)),
+        _ if layers.walls => palette::painted_wall_color(tile),
+        _ => None,
+    }
+}
+
+/// An RGBA image, with transparent pixels where there's nothing
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// Row major, 4 bytes per pixel
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32) -> Self {
+        Image {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+    pub fn pixel(&self, x: u32, y: u32) -> Rgba {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        self.pixels[i..i + 4].try_into().unwrap()
+    }
+    /// Pixels outside of the image are ignored
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Rgba) {
+        if x < self.width && y < self.height {
+            let i = (y as usize * self.width as usize + x as usize) * 4;
+            self.pixels[i..i + 4].copy_from_slice(&color);
+        }
+    }
+    /// Resize with nearest neighbour sampling, so tiles stay sharp when scaling up.
+    /// Fails if the result would be too big, see [`scaled_size`].
+    pub fn scaled(&self, scale: f64) -> Result<Image, String> {
+        let (width, height) = scaled_size(self.width, self.height, scale)?;
+        let mut scaled = Image::new(width, height);
+        for y in 0..height {
+            let src_y = ((f64::from(y) / scale) as u32).min(self.height - 1);
+            for x in 0..width {
+                let src_x = ((f64::from(x) / scale) as u32).min(self.width - 1);
+                scaled.set_pixel(x, y, self.pixel(src_x, src_y));
+            }
+        }
+        Ok(scaled)
+    }
+    /// A filled square centered on `x`, `y`, with a black outline so it stands out
+    pub fn draw_marker(&mut self, x: i64, y: i64, size: u32, color: Rgba) {
+        let half = i64::from(size / 2);
+        for my in y - half - 1..=y + half + 1 {
+            for mx in x - half - 1..=x + half + 1 {
+                if mx < 0 || my < 0 {
+                    continue;
+                }
+                let border = (mx - x).abs() > half || (my - y).abs() > half;
+                let color = if border { [0, 0, 0, 255] } else { color };
+                self.set_pixel(mx as u32, my as u32, color);
+            }
+        }
+    }
+    pub fn write_png(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+        Ok(())
+    }
+}
+
+/// Render `region` of the world, one pixel per tile
+pub fn render(
+    file: &File,
+    base_header: &BaseHeader,
+    layers: &Layers,
+    region: Region,
+) -> Result<Image, Box<dyn Error>> {
+    let mut image = Image::new(region.width.into(), region.height.into());
+    read_tiles(file, base_header, |tile, x, y| {
+        if !region.contains(x, y) {
+            return;
+        }
+        if let Some(color) = tile_pixel(&tile, layers) {
+            image.set_pixel(u32::from(x - region.x), u32::from(y - region.y), color);
+        }
+    })?;
+    Ok(image)
+}
+
+#[test]
+fn test_tile_pixel_fallback() {
+    let dirt_wall = palette::wall_color(2);
+    let tile = |front| Tile {
+        front,
+        back: Some(2),
+        ..Default::default()
+    };
+    let layers = Layers::default();
+    assert_eq!(
+        tile_pixel(&tile(Some(0)), &layers),
+        palette::tile_color(0, None)
+    );
+    // A chandelier has no map color, so the wall behind it shows
+    assert_eq!(tile_pixel(&tile(Some(34)), &layers), dirt_wall);
+    assert_eq!(tile_pixel(&tile(None), &layers), dirt_wall);
+    let no_walls = Layers {
+        walls: false,
+        ..layers
+    };
+    assert_eq!(tile_pixel(&tile(Some(34)), &no_walls), None);
+}
+
+#[test]
+fn test_scaled() {
+    let mut image = Image::new(2, 1);
+    image.set_pixel(1, 0, [1, 2, 3, 255]);
+    let up = image.scaled(2.0).unwrap();
+    assert_eq!((up.width, up.height), (4, 2));
+    assert_eq!(up.pixel(3, 1), [1, 2, 3, 255]);
+    assert_eq!(up.pixel(1, 1), [0, 0, 0, 0]);
+    let down = up.scaled(0.25).unwrap();
+    assert_eq!((down.width, down.height), (1, 1));
+    assert!(image.scaled(1e6).is_err());
+    assert!(scaled_size(8400, 2400, 16.0).is_err());
+    assert_eq!(scaled_size(8400, 2400, 2.0), Ok((16800, 4800)));
+}
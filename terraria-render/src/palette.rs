@@ -0,0 +1,196 @@
+//! Map colors of tiles, walls, liquids and paints, loaded from `map_colors.txt`
+
+use crate::Rgba;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use terraria_wld::{objects::object_style, Liquid, Tile, Wires};
+
+static MAP_COLORS: &str = include_str!("../map_colors.txt");
+
+/// Paint that inverts colors instead of tinting them
+const NEGATIVE_PAINT: u8 = 30;
+
+struct Palette {
+    /// Keyed by tile id and object style, `None` for the color of all styles. `None`
+    /// values are tiles whose map color is missing.
+    tiles: HashMap<(u16, Option<u16>), Option<Rgba>>,
+    default_tile: Rgba,
+    walls: HashMap<u16, Option<Rgba>>,
+    default_wall: Rgba,
+    liquids: HashMap<u16, Rgba>,
+    paints: HashMap<u8, Rgba>,
+}
+
+fn palette() -> &'static Palette {
+    static PALETTE: OnceLock<Palette> = OnceLock::new();
+    PALETTE.get_or_init(|| parse(MAP_COLORS).unwrap())
+}
+
+fn parse(text: &str) -> Result<Palette, String> {
+    let mut palette = Palette {
+        tiles: HashMap::new(),
+        default_tile: [255, 0, 255, 255],
+        walls: HashMap::new(),
+        default_wall: [180, 0, 180, 255],
+        liquids: HashMap::new(),
+        paints: HashMap::new(),
+    };
+    for (i, line) in text.lines().enumerate() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let err = |msg: &str| format!("map_colors.txt line {}: {}", i + 1, msg);
+        let mut fields = line.split('\t');
+        let (kind, id, color) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(kind), Some(id), Some(color)) => (kind, id, color),
+            _ => return Err(err("expected kind, id and color")),
+        };
+        let color = match color {
+            "missing" if kind == "tile" || kind == "wall" => None,
+            _ => Some(parse_color(color).ok_or_else(|| err("invalid color"))?),
+        };
+        let num = |s: &str| s.parse::<u16>().map_err(|_| err("invalid id"));
+        let required = || color.ok_or_else(|| err("a color is required"));
+        match (kind, id) {
+            ("tile", "default") => palette.default_tile = required()?,
+            ("wall", "default") => palette.default_wall = required()?,
+            ("tile", id) => {
+                let key = match id.split_once(':') {
+                    Some((id, style)) => (num(id)?, Some(num(style)?)),
+                    None => (num(id)?, None),
+                };
+                palette.tiles.insert(key, color);
+            }
+            ("wall", id) => {
+                palette.walls.insert(num(id)?, color);
+            }
+            ("liquid", id) => {
+                palette.liquids.insert(num(id)?, required()?);
+            }
+            ("paint", id) => {
+                let id = u8::try_from(num(id)?).map_err(|_| err("invalid id"))?;
+                palette.paints.insert(id, required()?);
+            }
+            _ => return Err(err("unknown kind")),
+        }
+    }
+    Ok(palette)
+}
+
+fn parse_color(hex: &str) -> Option<Rgba> {
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?, 255])
+}
+
+/// The color of tile `id`, for objects with a style of their own in the palette,
+/// like gems, the color of that style. `None` if its map color is missing.
+pub fn tile_color(id: u16, style: Option<u16>) -> Option<Rgba> {
+    let palette = palette();
+    style
+        .and_then(|style| palette.tiles.get(&(id, Some(style))))
+        .or_else(|| palette.tiles.get(&(id, None)))
+        .copied()
+        .unwrap_or(Some(palette.default_tile))
+}
+
+/// The color of wall `id`, `None` if its map color is missing
+pub fn wall_color(id: u16) -> Option<Rgba> {
+    let palette = palette();
+    palette
+        .walls
+        .get(&id)
+        .copied()
+        .unwrap_or(Some(palette.default_wall))
+}
+
+pub fn liquid_color(liquid: Liquid) -> Rgba {
+    let id = match liquid {
+        Liquid::Water => 0,
+        Liquid::Lava => 1,
+        Liquid::Honey => 2,
+        Liquid::Shimmer => 3,
+    };
+    palette().liquids[&id]
+}
+
+/// Tint `color` with a paint, keeping its brightness. Unknown paints leave it as is.
+pub fn apply_paint(color: Rgba, paint: u8) -> Rgba {
+    let [r, g, b, a] = color;
+    if paint == NEGATIVE_PAINT {
+        return [255 - r, 255 - g, 255 - b, a];
+    }
+    let paint_color = match palette().paints.get(&paint) {
+        Some(paint_color) => paint_color,
+        None => return color,
+    };
+    let brightness = f32::from(r.max(g).max(b)) / 255.0;
+    let tint = |channel: u8| (f32::from(channel) * brightness).round() as u8;
+    [
+        tint(paint_color[0]),
+        tint(paint_color[1]),
+        tint(paint_color[2]),
+        a,
+    ]
+}
+
+/// The color of the block of a tile, with its paint applied
+pub fn block_color(tile: &Tile) -> Option<Rgba> {
+    let color = tile_color(tile.front?, object_style(tile))?;
+    Some(apply_paint(color, tile.paint))
+}
+
+/// The color of the wall of a tile, with its paint applied
+pub fn painted_wall_color(tile: &Tile) -> Option<Rgba> {
+    Some(apply_paint(wall_color(tile.back?)?, tile.wall_paint))
+}
+
+/// Wires are drawn in the color of the first one present, in this order
+pub fn wire_color(wires: Wires) -> Option<Rgba> {
+    if wires.red {
+        Some([255, 0, 0, 255])
+    } else if wires.blue {
+        Some([0, 0, 255, 255])
+    } else if wires.green {
+        Some([0, 255, 0, 255])
+    } else if wires.yellow {
+        Some([255, 255, 0, 255])
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_palette() {
+    let palette = palette();
+    assert_eq!(palette.liquids.len(), Liquid::ALL.len());
+    assert_eq!(palette.paints.len(), 30);
+    // Dirt, and the amethyst style of exposed gems
+    assert_eq!(tile_color(0, None), Some([151, 107, 75, 255]));
+    assert_eq!(tile_color(178, Some(0)), Some([208, 80, 255, 255]));
+    assert_eq!(tile_color(60000, None), Some(palette.default_tile));
+    // Chandelier, whose map color isn't known
+    assert_eq!(tile_color(34, None), None);
+    assert!(parse("tile\tdefault\tmissing\tUnknown").is_err());
+    // Red paint on gray stone
+    assert_eq!(apply_paint([128, 128, 128, 255], 1), [128, 0, 0, 255]);
+    assert!(parse("tile\t1\tzzzzzz\tStone").is_err());
+}
+
+#[test]
+fn test_palette_complete() {
+    let palette = palette();
+    let ids = |list: &str| -> Vec<u16> {
+        list.lines()
+            .map(|line| line.split('\t').next().unwrap().parse().unwrap())
+            .collect()
+    };
+    for id in ids(terraria_ids::TILE_ID_LIST) {
+        assert!(palette.tiles.contains_key(&(id, None)), "tile {}", id);
+    }
+    for id in ids(terraria_ids::WALL_ID_LIST) {
+        assert!(palette.walls.contains_key(&id), "wall {}", id);
+    }
+}
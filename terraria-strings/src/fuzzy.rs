@@ -0,0 +1,55 @@
+//! Fuzzy string matching for item name searches
+
+/// How similar `query` is to `name`, from 0.0 (nothing in common) to 1.0 (equal).
+///
+/// Both strings are expected to be lowercased already.
+/// Substring matches always score above 0.5, the rest is scored by edit distance.
+pub fn similarity(query: &str, name: &str) -> f64 {
+    if query == name {
+        return 1.0;
+    }
+    let query_len = query.chars().count();
+    let name_len = name.chars().count();
+    if query_len == 0 || name_len == 0 {
+        return 0.0;
+    }
+    if name.contains(query) {
+        return 0.5 + 0.5 * query_len as f64 / name_len as f64;
+    }
+    let name: Vec<char> = name.chars().collect();
+    let whole = 1.0 - edit_distance(query, &name).1 as f64 / query_len.max(name_len) as f64;
+    // Also try to match the query against the beginning of each word, so misspelled
+    // partial names like "hremes" still find "hermes boots".
+    let mut partial: f64 = 0.0;
+    if query_len >= 3 {
+        for i in (0..name.len()).filter(|&i| i == 0 || name[i - 1] == ' ') {
+            let dist = edit_distance(query, &name[i..]).0;
+            partial = partial.max(1.0 - dist as f64 / query_len as f64);
+        }
+    }
+    (whole * 0.9).max(partial * 0.75)
+}
+
+/// Returns the distance between `a` and the closest prefix of `b`,
+/// and the plain Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &[char]) -> (usize, usize) {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let subst = prev[j] + usize::from(ca != cb);
+            cur[j + 1] = subst.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    (*prev.iter().min().unwrap(), prev[b.len()])
+}
+
+#[test]
+fn test_edit_distance() {
+    let chars = |s: &str| s.chars().collect::<Vec<_>>();
+    assert_eq!(edit_distance("kitten", &chars("sitting")).1, 3);
+    assert_eq!(edit_distance("", &chars("abc")).1, 3);
+    assert_eq!(edit_distance("hremes", &chars("hermes boots")), (2, 8));
+}
@@ -1,24 +1,227 @@
-pub struct ItemIdMap(Vec<(u16, &'static str)>);
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::OnceLock;
 
-impl ItemIdMap {
-    pub fn name_by_id(&self, id: u16) -> Option<&str> {
-        self.0.iter().find(|pair| pair.0 == id).map(|pair| pair.1)
+mod fuzzy;
+mod locale;
+
+pub use locale::{parse_item_names, Locale};
+pub use terraria_ids::{ItemId, TileId, WallId};
+use terraria_ids::{ITEM_ID_LIST, TILE_ID_LIST, WALL_ID_LIST};
+
+/// Maps between the ids and names of items, tiles or walls
+pub struct IdMap {
+    /// (id, name, internal name) triples, in the order of the id list
+    entries: Vec<(u16, &'static str, &'static str)>,
+    /// Maps ids to their index in `entries`
+    by_id: HashMap<u16, usize>,
+    /// Lowercased names to ids
+    by_name: HashMap<String, u16>,
+    /// Lowercased internal names (like `IronPickaxe`) to ids
+    by_internal_name: HashMap<String, u16>,
+}
+
+/// The item id map used to be the only one, and most code still refers to it by this name
+pub type ItemIdMap = IdMap;
+
+impl IdMap {
+    fn from_list(list: &'static str) -> Self {
+        let mut map = IdMap {
+            entries: Vec::new(),
+            by_id: HashMap::new(),
+            by_name: HashMap::new(),
+            by_internal_name: HashMap::new(),
+        };
+        for line in list.lines() {
+            let mut parts = line.split('\t');
+            let id: u16 = parts.next().unwrap().parse().unwrap();
+            let name = parts.next().unwrap();
+            let internal_name = parts.next().unwrap();
+            map.by_id.entry(id).or_insert(map.entries.len());
+            // Some names are shared by multiple entries. The first one wins.
+            map.by_name.entry(name.to_lowercase()).or_insert(id);
+            map.by_internal_name
+                .entry(internal_name.to_lowercase())
+                .or_insert(id);
+            map.entries.push((id, name, internal_name));
+        }
+        map
     }
+    /// Create a copy of this map with the names replaced by localized ones.
+    ///
+    /// `names` maps internal names to localized names. Items missing from it keep their
+    /// original names. Lookups by the original names keep working.
+    fn localized(&self, names: &'static HashMap<String, String>) -> Self {
+        let mut map = IdMap {
+            entries: Vec::new(),
+            by_id: self.by_id.clone(),
+            by_name: HashMap::new(),
+            by_internal_name: self.by_internal_name.clone(),
+        };
+        for &(id, name, internal_name) in &self.entries {
+            let localized = names.get(internal_name).map_or(name, String::as_str);
+            map.by_name.entry(localized.to_lowercase()).or_insert(id);
+            map.entries.push((id, localized, internal_name));
+        }
+        for (name, &id) in &self.by_name {
+            map.by_name.entry(name.clone()).or_insert(id);
+        }
+        map
+    }
+    pub fn name_by_id(&self, id: u16) -> Option<&'static str> {
+        self.by_id.get(&id).map(|&idx| self.entries[idx].1)
+    }
+    pub fn internal_name_by_id(&self, id: u16) -> Option<&'static str> {
+        self.by_id.get(&id).map(|&idx| self.entries[idx].2)
+    }
+    /// Look up an id by its name. Case insensitive.
     pub fn id_by_name(&self, name: &str) -> Option<u16> {
-        self.0.iter().find(|pair| pair.1 == name).map(|pair| pair.0)
+        self.by_name.get(&name.to_lowercase()).copied()
+    }
+    /// Look up an id by its internal name, like `IronPickaxe`. Case insensitive.
+    pub fn id_by_internal_name(&self, name: &str) -> Option<u16> {
+        self.by_internal_name.get(&name.to_lowercase()).copied()
+    }
+    /// Look up an id by either its name or its internal name
+    pub fn id_by_any_name(&self, name: &str) -> Option<u16> {
+        self.id_by_name(name)
+            .or_else(|| self.id_by_internal_name(name))
+    }
+    /// Iterate over all (id, name) pairs
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &'static str)> + '_ {
+        self.entries.iter().map(|&(id, name, _)| (id, name))
+    }
+    /// Search for entries whose name or internal name resembles `query`.
+    ///
+    /// Returns matches scoring at least `min_score` (see [`SearchMatch::score`]),
+    /// best matches first.
+    pub fn search(&self, query: &str, min_score: f64) -> Vec<SearchMatch> {
+        let query = query.to_lowercase();
+        let mut matches: Vec<SearchMatch> = self
+            .entries
+            .iter()
+            .filter_map(|&(id, name, internal_name)| {
+                let score = fuzzy::similarity(&query, &name.to_lowercase())
+                    .max(fuzzy::similarity(&query, &internal_name.to_lowercase()));
+                (score >= min_score).then_some(SearchMatch { id, name, score })
+            })
+            .collect();
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+        matches
+    }
+    /// Names of the entries that most closely resemble `name`, for "did you mean" hints
+    pub fn suggestions(&self, name: &str, max: usize) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        for m in self.search(name, SUGGESTION_MIN_SCORE) {
+            if names.len() == max {
+                break;
+            }
+            if !names.contains(&m.name) {
+                names.push(m.name);
+            }
+        }
+        names
     }
 }
 
-pub fn item_ids() -> ItemIdMap {
-    let mut item_ids = Vec::new();
-    for line in ITEM_ID_LIST.lines() {
-        let mut parts = line.split('\t');
-        let id: u16 = parts.next().unwrap().parse().unwrap();
-        let name = parts.next().unwrap();
-        item_ids.push((id, name));
-    }
-    ItemIdMap(item_ids)
+/// Below this score, a match is too different to be worth suggesting
+const SUGGESTION_MIN_SCORE: f64 = 0.6;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SearchMatch {
+    pub id: u16,
+    pub name: &'static str,
+    /// How similar the name is to the query, from 0.0 to 1.0
+    pub score: f64,
 }
 
-/// These are taken from https://terraria.fandom.com/wiki/Item_IDs
-static ITEM_ID_LIST: &str = include_str!("../item_id_list.txt");
+static LOCALIZED_ITEM_IDS: OnceLock<ItemIdMap> = OnceLock::new();
+/// The localized names [`LOCALIZED_ITEM_IDS`] borrows from, keyed by internal name
+static LOCALIZED_ITEM_NAMES: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+fn english_item_ids() -> &'static ItemIdMap {
+    static MAP: OnceLock<ItemIdMap> = OnceLock::new();
+    MAP.get_or_init(|| ItemIdMap::from_list(ITEM_ID_LIST))
+}
+
+/// The item id map, with localized names if [`set_item_localization`] was called
+pub fn item_ids() -> &'static ItemIdMap {
+    LOCALIZED_ITEM_IDS.get().unwrap_or_else(english_item_ids)
+}
+
+/// The tile id map. Tile names are always English.
+pub fn tile_ids() -> &'static IdMap {
+    static MAP: OnceLock<IdMap> = OnceLock::new();
+    MAP.get_or_init(|| IdMap::from_list(TILE_ID_LIST))
+}
+
+/// The wall id map. Wall names are always English.
+pub fn wall_ids() -> &'static IdMap {
+    static MAP: OnceLock<IdMap> = OnceLock::new();
+    MAP.get_or_init(|| IdMap::from_list(WALL_ID_LIST))
+}
+
+/// Use the item names from a Terraria localization file for all later [`item_ids`] calls.
+///
+/// Can only be done once.
+pub fn set_item_localization(json: &str) -> Result<(), Box<dyn Error>> {
+    let names = parse_item_names(json)?;
+    LOCALIZED_ITEM_NAMES
+        .set(names)
+        .map_err(|_| "Item localization was already set")?;
+    let names = LOCALIZED_ITEM_NAMES.get().unwrap();
+    LOCALIZED_ITEM_IDS
+        .set(english_item_ids().localized(names))
+        .map_err(|_| "Item localization was already set")?;
+    Ok(())
+}
+
+#[test]
+fn test_lookups() {
+    let ids = item_ids();
+    assert_eq!(ids.id_by_name("Hermes Boots"), Some(54));
+    assert_eq!(ids.id_by_name("hermes BOOTS"), Some(54));
+    assert_eq!(ids.id_by_internal_name("hermesboots"), Some(54));
+    assert_eq!(ids.id_by_any_name("IronPickaxe"), Some(1));
+    assert_eq!(ids.name_by_id(1), Some("Iron Pickaxe"));
+    assert_eq!(ids.internal_name_by_id(1), Some("IronPickaxe"));
+}
+
+#[test]
+fn test_localized() {
+    static NAMES: OnceLock<HashMap<String, String>> = OnceLock::new();
+    let names = NAMES.get_or_init(|| {
+        parse_item_names(r#"{"ItemName": {"HermesBoots": "Hermesstiefel"}}"#).unwrap()
+    });
+    let ids = english_item_ids().localized(names);
+    assert_eq!(ids.name_by_id(54), Some("Hermesstiefel"));
+    assert_eq!(ids.id_by_name("hermesstiefel"), Some(54));
+    assert_eq!(ids.id_by_name("Hermes Boots"), Some(54));
+    assert_eq!(ids.name_by_id(1), Some("Iron Pickaxe"));
+}
+
+#[test]
+fn test_suggestions() {
+    let ids = item_ids();
+    assert_eq!(ids.suggestions("Hermes Boot", 1), vec!["Hermes Boots"]);
+    assert_eq!(ids.suggestions("Hremes Boots", 1), vec!["Hermes Boots"]);
+    assert!(ids.suggestions("qqqqqqqqqqqqqqqq", 3).is_empty());
+    assert!(ids.suggestions("Hermes Boot", 0).is_empty());
+}
+
+#[test]
+fn test_consts() {
+    let ids = item_ids();
+    assert_eq!(
+        ids.name_by_id(ItemId::HERMES_BOOTS.into()),
+        Some("Hermes Boots")
+    );
+    assert_eq!(
+        tile_ids().id_by_any_name("life crystal").map(TileId),
+        Some(TileId::HEART)
+    );
+    assert_eq!(
+        wall_ids().name_by_id(WallId::LIHZAHRD_BRICK_UNSAFE.into()),
+        Some("Lihzahrd Brick Wall (natural)")
+    );
+}
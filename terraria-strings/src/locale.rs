@@ -0,0 +1,85 @@
+//! Localized item names
+//!
+//! Terraria ships its translations as JSON files, one per language, with item names
+//! keyed by their internal names:
+//!
+//! ```json
+//! { "ItemName": { "IronPickaxe": "Eisenspitzhacke", ... } }
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error;
+
+/// The languages the game ships with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    English,
+    German,
+    Italian,
+    French,
+    Spanish,
+    Russian,
+    Chinese,
+    Portuguese,
+    Polish,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 9] = [
+        Locale::English,
+        Locale::German,
+        Locale::Italian,
+        Locale::French,
+        Locale::Spanish,
+        Locale::Russian,
+        Locale::Chinese,
+        Locale::Portuguese,
+        Locale::Polish,
+    ];
+    /// The culture code Terraria uses for this locale, like `de-DE`
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::English => "en-US",
+            Locale::German => "de-DE",
+            Locale::Italian => "it-IT",
+            Locale::French => "fr-FR",
+            Locale::Spanish => "es-ES",
+            Locale::Russian => "ru-RU",
+            Locale::Chinese => "zh-Hans",
+            Locale::Portuguese => "pt-BR",
+            Locale::Polish => "pl-PL",
+        }
+    }
+    /// Accepts full culture codes (`pt-BR`) as well as just the language part (`pt`).
+    /// Case insensitive.
+    pub fn from_code(code: &str) -> Option<Self> {
+        let code = code.to_lowercase();
+        Self::ALL.into_iter().find(|locale| {
+            let full = locale.code().to_lowercase();
+            full == code || full.split('-').next() == Some(&code)
+        })
+    }
+}
+
+/// Read the item names out of a Terraria localization file.
+///
+/// Returns a map of internal names to localized names.
+pub fn parse_item_names(json: &str) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let root: serde_json::Value = serde_json::from_str(json)?;
+    let names = root
+        .get("ItemName")
+        .and_then(|v| v.as_object())
+        .ok_or("Localization file has no ItemName section")?;
+    Ok(names
+        .iter()
+        .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_owned())))
+        .collect())
+}
+
+#[test]
+fn test_from_code() {
+    assert_eq!(Locale::from_code("de-DE"), Some(Locale::German));
+    assert_eq!(Locale::from_code("pt"), Some(Locale::Portuguese));
+    assert_eq!(Locale::from_code("ZH-hans"), Some(Locale::Chinese));
+    assert_eq!(Locale::from_code("xx"), None);
+}
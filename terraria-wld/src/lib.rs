@@ -7,7 +7,13 @@ use std::io::prelude::*;
 use std::io::{self, SeekFrom};
 use std::path::Path;
 
-#[derive(Clone)]
+pub mod nbt;
+pub mod objects;
+pub mod structures;
+pub mod tmod;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Header {
     pub id: i32,
     pub bounds: Rect,
@@ -21,9 +27,16 @@ pub struct Header {
     pub generator_version: i64,
     pub guid: [u8; 16],
     pub game_mode: i32,
+    pub rock_y: f64,
+    pub dungeon_x: i32,
+    pub dungeon_y: i32,
+    /// The world evil is crimson, otherwise it's corruption
+    pub crimson: bool,
+    pub hardmode: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rect {
     pub left: i32,
     pub right: i32,
@@ -46,6 +59,9 @@ pub fn read_npcs(mut file: &File, offset: u64) -> Result<Vec<Npc>, Box<dyn Error
     Ok(npcs)
 }
 pub fn read_header(mut f: &File, offset: u64) -> Result<Header, Box<dyn Error>> {
+    // The fields present depend on the version, which is at the very start of the file
+    f.seek(SeekFrom::Start(0))?;
+    let version = f.read_i32::<LE>()?;
     f.seek(SeekFrom::Start(offset))?;
     let name = read_string(f)?;
     let seed = read_string(f)?;
@@ -57,7 +73,13 @@ pub fn read_header(mut f: &File, offset: u64) -> Result<Header, Box<dyn Error>>
     let height = f.read_i32::<LE>()?;
     let width = f.read_i32::<LE>()?;
     let game_mode = f.read_i32::<LE>()?;
-    let _expert = f.read_u8()?;
+    // Secret seed flags: drunk world, for the worthy, 10th anniversary, the constant,
+    // not the bees, remix, no traps, zenith
+    for min_version in [222, 227, 238, 239, 241, 249, 266, 267] {
+        if version >= min_version {
+            let _secret_seed = f.read_u8()?;
+        }
+    }
     let _creation_time = f.read_i64::<LE>()?;
     let _moon_type = f.read_u8()?;
     let _tree_x_1 = f.read_i32::<LE>()?;
@@ -80,6 +102,23 @@ pub fn read_header(mut f: &File, offset: u64) -> Result<Header, Box<dyn Error>>
     let spawn_x = f.read_i32::<LE>()?;
     let spawn_y = f.read_i32::<LE>()?;
     let surface_y = f.read_f64::<LE>()?;
+    let rock_y = f.read_f64::<LE>()?;
+    let _time = f.read_f64::<LE>()?;
+    let _day_time = f.read_u8()?;
+    let _moon_phase = f.read_i32::<LE>()?;
+    let _blood_moon = f.read_u8()?;
+    let _eclipse = f.read_u8()?;
+    let dungeon_x = f.read_i32::<LE>()?;
+    let dungeon_y = f.read_i32::<LE>()?;
+    let crimson = f.read_u8()? != 0;
+    // Downed bosses, saved NPCs and defeated invasions
+    let mut _progress = [0; 18];
+    f.read_exact(&mut _progress)?;
+    let _shadow_orb_smashed = f.read_u8()?;
+    let _spawn_meteor = f.read_u8()?;
+    let _shadow_orb_count = f.read_u8()?;
+    let _altar_count = f.read_i32::<LE>()?;
+    let hardmode = f.read_u8()? != 0;
     Ok(Header {
         width: width as u16,
         height: height as u16,
@@ -93,6 +132,11 @@ pub fn read_header(mut f: &File, offset: u64) -> Result<Header, Box<dyn Error>>
         bounds,
         guid,
         game_mode,
+        rock_y,
+        dungeon_x,
+        dungeon_y,
+        crimson,
+        hardmode,
     })
 }
 pub fn read_chest_types(
@@ -136,6 +180,9 @@ pub fn write_chests(
     let new_signs_offset = file.seek(SeekFrom::Current(0))?;
     // Write back everything after chests
     file.write_all(&rest_buf)?;
+    // Fewer or shorter chests leave the old end of the file behind
+    let end = file.stream_position()?;
+    file.set_len(end)?;
     let offs_diff = new_signs_offset as i32 - base_header.offsets.signs as i32;
     file.seek(SeekFrom::Start(OFFSET_TABLE_OFFSET))?;
     base_header.offsets.signs += offs_diff;
@@ -156,6 +203,55 @@ fn write_chests_inner(mut f: &File, chests: &[Chest]) -> Result<(), Box<dyn Erro
     }
     Ok(())
 }
+
+#[test]
+fn test_write_fewer_chests() {
+    let path = std::env::temp_dir().join(format!("write-chests-{}.wld", std::process::id()));
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .unwrap();
+    let chest = |x| Chest {
+        x,
+        y: 0,
+        name: String::new(),
+        items: [Item::default(); CHEST_MAX_ITEMS as usize],
+        modded_items: Vec::new(),
+    };
+    (&file).write_all(&[0; 100]).unwrap();
+    write_chests_inner(&file, &[chest(1), chest(2)]).unwrap();
+    let signs = (&file).stream_position().unwrap() as i32;
+    (&file).write_all(b"signs").unwrap();
+    let offsets = Offsets {
+        header: 0,
+        tiles: 0,
+        chests: 100,
+        signs,
+        npcs: signs,
+        entities: signs,
+        footer: signs,
+        unused_1: 0,
+        unused_2: 0,
+        unused_3: 0,
+        unknown_4: 0,
+    };
+    let mut base_header = BaseHeader {
+        version: 0,
+        offsets,
+        times_saved: 0,
+        is_favorite: 0,
+        tile_frame_important: Vec::new(),
+    };
+    write_chests(&file, &mut base_header, &[chest(1)]).unwrap();
+    let len = file.metadata().unwrap().len();
+    let chests = read_chests(&file, 100).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(chests.len(), 1);
+    assert_eq!(len, base_header.offsets.signs as u64 + 5);
+}
 /// New, more accurate version
 pub fn read_tiles<TC>(
     mut file: &File,
@@ -186,50 +282,88 @@ fn load_chest_types(
     file: &File,
     base_header: &BaseHeader,
 ) -> Result<HashMap<(u16, u16), ChestType>, Box<dyn Error>> {
-    let mut chest_types = HashMap::new();
-    read_tiles(file, base_header, |tile, x, y| {
-        if tile.front == Some(21) {
-            let tfo = tile.frame.unwrap();
-            if tfo.y == 0 {
-                let type_ = ChestType::from_frame_x(tfo.x);
-                chest_types.insert((x, y), type_);
-            }
-        } else if tile.front == Some(88)
-        /* dresser */
-        {
-            let tfo = tile.frame.unwrap();
-            chest_types.insert((x, y), ChestType::UnknownDresser(tfo.x));
-        }
-    })?;
-    Ok(chest_types)
+    let objects = objects::read_objects(file, base_header, |id| matches!(id, 21 | 88 | 467))?;
+    Ok(objects
+        .into_iter()
+        .map(|object| {
+            let type_ = match object.tile_id {
+                21 => ChestType::from_frame_x(object.frame_x),
+                // The chests added in 1.4, which didn't fit in the first tile's sheet
+                467 => ChestType::from_frame_x_2(object.frame_x),
+                _ => ChestType::UnknownDresser(object.frame_x),
+            };
+            ((object.x, object.y), type_)
+        })
+        .collect())
 }
 
 #[derive(Clone, Copy, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tile {
     pub front: Option<u16>,
     pub back: Option<u16>,
     pub liquid: Option<Liquid>,
     pub frame: Option<TileFrameOffset>,
+    pub wires: Wires,
+    /// Paint of the block, 0 if unpainted
+    pub paint: u8,
+    /// Paint of the wall, 0 if unpainted
+    pub wall_paint: u8,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Wires {
+    pub red: bool,
+    pub blue: bool,
+    pub green: bool,
+    pub yellow: bool,
+}
+
+impl Wires {
+    pub fn any(self) -> bool {
+        self.red || self.blue || self.green || self.yellow
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Liquid {
     Water,
     Lava,
     Honey,
+    Shimmer,
 }
 
-fn read_tile(mut file: &File, tile_frame_important: &[u8]) -> io::Result<(Tile, u16)> {
+impl Liquid {
+    pub const ALL: [Liquid; 4] = [Liquid::Water, Liquid::Lava, Liquid::Honey, Liquid::Shimmer];
+    pub fn name(self) -> &'static str {
+        match self {
+            Liquid::Water => "Water",
+            Liquid::Lava => "Lava",
+            Liquid::Honey => "Honey",
+            Liquid::Shimmer => "Shimmer",
+        }
+    }
+}
+
+fn read_tile(mut file: impl Read, tile_frame_important: &[u8]) -> io::Result<(Tile, u16)> {
     let flags1 = file.read_u8()?;
-    let flags2;
+    let mut flags2 = 0;
     let mut flags3 = 0;
     if flags1.nth_bit_set(0) {
         flags2 = file.read_u8()?;
         if flags2.nth_bit_set(0) {
             flags3 = file.read_u8()?;
+            if flags3.nth_bit_set(0) {
+                // Coatings, which we don't care about
+                let _flags4 = file.read_u8()?;
+            }
         }
     }
     let mut tile_frame = None;
+    let mut paint = 0;
+    let mut wall_paint = 0;
     let front = if flags1.nth_bit_set(1) {
         let mut type_inner = file.read_u8()? as u16;
         if flags1.nth_bit_set(5) {
@@ -242,7 +376,7 @@ fn read_tile(mut file: &File, tile_frame_important: &[u8]) -> io::Result<(Tile,
             })
         }
         if flags3.nth_bit_set(3) {
-            let _color = file.read_u8()?;
+            paint = file.read_u8()?;
         }
         Some(type_inner)
     } else {
@@ -252,7 +386,7 @@ fn read_tile(mut file: &File, tile_frame_important: &[u8]) -> io::Result<(Tile,
     if flags1.nth_bit_set(2) {
         back = Some(file.read_u8()? as u16);
         if flags3.nth_bit_set(4) {
-            let _wall_color = file.read_u8()?;
+            wall_paint = file.read_u8()?;
         }
     }
     let liquid = match flags1 & 0b00011000 {
@@ -260,6 +394,7 @@ fn read_tile(mut file: &File, tile_frame_important: &[u8]) -> io::Result<(Tile,
         liquid => Some({
             let _liquid_amount = file.read_u8()?;
             match liquid {
+                _ if flags3.nth_bit_set(7) => Liquid::Shimmer,
                 0b00001000 => Liquid::Water,
                 0b00010000 => Liquid::Lava,
                 0b00011000 => Liquid::Honey,
@@ -283,6 +418,14 @@ fn read_tile(mut file: &File, tile_frame_important: &[u8]) -> io::Result<(Tile,
             back,
             liquid,
             frame: tile_frame,
+            wires: Wires {
+                red: flags2.nth_bit_set(1),
+                blue: flags2.nth_bit_set(2),
+                green: flags2.nth_bit_set(3),
+                yellow: flags3.nth_bit_set(5),
+            },
+            paint,
+            wall_paint,
         },
         rle,
     ))
@@ -299,6 +442,7 @@ fn read_rect(mut f: &File) -> io::Result<Rect> {
 
 /// Contains the offsets of different sections, and some other base information.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BaseHeader {
     pub version: i32,
     pub offsets: Offsets,
@@ -307,8 +451,21 @@ pub struct BaseHeader {
     pub tile_frame_important: Vec<u8>,
 }
 
+impl BaseHeader {
+    /// Whether tiles with this id store their frame, which is the case for objects and
+    /// other decorative tiles, but not for blocks
+    pub fn is_frame_important(&self, id: u16) -> bool {
+        usize::from(id) < self.tile_frame_important.len() * 8
+            && self
+                .tile_frame_important
+                .as_slice()
+                .nth_bit_set(usize::from(id))
+    }
+}
+
 /// The offsets of different sections
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Offsets {
     pub header: i32,
     pub tiles: i32,
@@ -416,6 +573,37 @@ impl<'a> Bits for &'a [u8] {
     }
 }
 
+#[test]
+fn test_read_tile_flags4_shimmer() {
+    let frame_important = [0u8; 80];
+    let data = [
+        // flags2 follows, has a tile, water
+        0b0000_1011,
+        // flags3 follows
+        0b0000_0001,
+        // flags4 follows, shimmer
+        0b1000_0001,
+        // flags4, coatings
+        0b0000_0011,
+        // stone, liquid amount
+        1,
+        255,
+        // The next tile: dirt, u8 repeat of 5
+        0b0100_0010,
+        0,
+        5,
+    ];
+    let mut reader = &data[..];
+    let (tile, rle) = read_tile(&mut reader, &frame_important).unwrap();
+    assert_eq!(
+        (tile.front, tile.liquid, rle),
+        (Some(1), Some(Liquid::Shimmer), 0)
+    );
+    let (tile, rle) = read_tile(&mut reader, &frame_important).unwrap();
+    assert_eq!((tile.front, tile.liquid, rle), (Some(0), None, 5));
+    assert!(reader.is_empty());
+}
+
 #[test]
 fn test_bits_u8() {
     assert!(0b0000_0001.nth_bit_set(0));
@@ -425,6 +613,7 @@ fn test_bits_u8() {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ChestType {
     Plain,
     Gold,
@@ -445,7 +634,12 @@ pub enum ChestType {
     LockedJungle,
     LockedFrozen,
     Lihzahrd,
+    Sandstone,
+    DeadMans,
+    LockedDesert,
     UnknownChest(u16),
+    /// A chest of the second chest tile that isn't one of the above
+    UnknownChest2(u16),
     UnknownDresser(u16),
 }
 
@@ -475,6 +669,15 @@ impl ChestType {
             _ => UnknownChest(frame_x),
         }
     }
+    fn from_frame_x_2(frame_x: u16) -> Self {
+        use self::ChestType::*;
+        match frame_x {
+            144 => DeadMans,
+            360 => Sandstone,
+            468 => LockedDesert,
+            _ => UnknownChest2(frame_x),
+        }
+    }
 }
 
 /// The offset of the subimage a tile has.
@@ -501,12 +704,98 @@ impl ChestType {
 /// Which tile frames are important are stored in an array called tile_frame_important in the .wld
 /// file, which are read along with other metadata.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TileFrameOffset {
     pub x: u16,
     pub y: u16,
 }
 
+/// How many tiles from the bottom of the world the underworld starts
+const UNDERWORLD_HEIGHT: f64 = 200.0;
+
+/// How far from the world edges the oceans reach, like the game's beach zone
+pub const OCEAN_WIDTH: u16 = 380;
+
+/// The depth layers of a world, from top to bottom. Space is counted as surface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Layer {
+    Surface,
+    Underground,
+    Cavern,
+    Underworld,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 4] = [
+        Layer::Surface,
+        Layer::Underground,
+        Layer::Cavern,
+        Layer::Underworld,
+    ];
+    pub fn name(self) -> &'static str {
+        match self {
+            Layer::Surface => "surface",
+            Layer::Underground => "underground",
+            Layer::Cavern => "cavern",
+            Layer::Underworld => "underworld",
+        }
+    }
+}
+
 impl Header {
+    /// The layer tile row `y` belongs to
+    pub fn layer(&self, y: u16) -> Layer {
+        let y = f64::from(y);
+        if y < self.surface_y {
+            Layer::Surface
+        } else if y < self.rock_y {
+            Layer::Underground
+        } else if y < f64::from(self.height) - UNDERWORLD_HEIGHT {
+            Layer::Cavern
+        } else {
+            Layer::Underworld
+        }
+    }
+    pub fn game_mode_name(&self) -> &'static str {
+        match self.game_mode {
+            0 => "Normal",
+            1 => "Expert",
+            2 => "Master",
+            3 => "Journey",
+            _ => "Unknown",
+        }
+    }
+    /// Small, medium or large, going by the width. Worlds of any other size are "custom".
+    pub fn size_class(&self) -> &'static str {
+        match self.width {
+            4200 => "small",
+            6400 => "medium",
+            8400 => "large",
+            _ => "custom",
+        }
+    }
+    /// The GUID formatted the way .NET formats it, like `8a1d34a0-1ff2-4b5b-9b8c-3c2f7f4e0e61`.
+    ///
+    /// .NET stores the first three groups of the GUID in little endian order, so the bytes
+    /// need to be shuffled around.
+    pub fn guid_string(&self) -> String {
+        let g = &self.guid;
+        let hex = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        };
+        format!(
+            "{}-{}-{}-{}-{}",
+            hex(&[g[3], g[2], g[1], g[0]]),
+            hex(&[g[5], g[4]]),
+            hex(&[g[7], g[6]]),
+            hex(&g[8..10]),
+            hex(&g[10..])
+        )
+    }
     pub fn tile_to_gps_pos(&self, x: u16, y: u16) -> GpsPos {
         let raw_x = i32::from(x) * 2 - i32::from(self.width);
         let raw_y = self.surface_y * 2.0 - f64::from(y) * 2.0;
@@ -525,6 +814,8 @@ impl Header {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GpsPos {
     x_offset: u32,
     y_offset: u32,
@@ -550,11 +841,15 @@ impl fmt::Display for GpsPos {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum XSide {
     West,
     East,
 }
 
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum YSide {
     AboveSurface,
     BelowSurface,
@@ -562,11 +857,59 @@ pub enum YSide {
 
 const CHEST_MAX_ITEMS: i8 = 40;
 
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Chest {
     pub x: u16,
     pub y: u16,
     pub name: String,
+    #[cfg_attr(feature = "serde", serde(with = "serde_items"))]
     pub items: [Item; CHEST_MAX_ITEMS as usize],
+    /// Items from tModLoader's `.twld` file, see [`tmod::ModData::merge_chests`].
+    /// These are not written back by [`write_chests`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub modded_items: Vec<tmod::ModdedItem>,
+}
+
+/// Serde only supports arrays of up to 32 elements, so chest items go through a `Vec`
+#[cfg(feature = "serde")]
+mod serde_items {
+    use super::{Item, CHEST_MAX_ITEMS};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(items: &[Item], ser: S) -> Result<S::Ok, S::Error> {
+        ser.collect_seq(items)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        de: D,
+    ) -> Result<[Item; CHEST_MAX_ITEMS as usize], D::Error> {
+        let items = Vec::<Item>::deserialize(de)?;
+        let len = items.len();
+        items
+            .try_into()
+            .map_err(|_| D::Error::invalid_length(len, &"an array of 40 items"))
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_chest_items() {
+    let mut chest = Chest {
+        x: 10,
+        y: 20,
+        name: String::new(),
+        items: [Item::default(); CHEST_MAX_ITEMS as usize],
+        modded_items: Vec::new(),
+    };
+    chest.items[3] = Item {
+        stack: 5,
+        id: 8,
+        prefix_id: 0,
+    };
+    let json = serde_json::to_string(&chest).unwrap();
+    let back: Chest = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.items[3].id, 8);
+    assert!(serde_json::from_str::<Chest>(r#"{"x":0,"y":0,"name":"","items":[]}"#).is_err());
 }
 
 impl Chest {
@@ -578,7 +921,21 @@ impl Chest {
         for item in &mut items[..] {
             *item = Item::read(f)?;
         }
-        Ok(Self { x, y, name, items })
+        Ok(Self {
+            x,
+            y,
+            name,
+            items,
+            modded_items: Vec::new(),
+        })
+    }
+    /// Whether neither a vanilla nor a modded item occupies the slot
+    pub fn slot_is_free(&self, slot: usize) -> bool {
+        self.items[slot].stack == 0
+            && !self
+                .modded_items
+                .iter()
+                .any(|it| usize::from(it.slot) == slot)
     }
     fn write(&self, mut f: &File) -> io::Result<()> {
         f.write_i32::<LE>(i32::from(self.x))?;
@@ -622,7 +979,8 @@ fn read_string_len(mut f: &File) -> io::Result<usize> {
     Ok(len)
 }
 
-#[derive(Default, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Item {
     pub stack: u16,
     pub id: i32,
@@ -689,6 +1047,8 @@ fn write_npc(mut f: &File, npc: &Npc) -> io::Result<()> {
     Ok(())
 }
 
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Npc {
     pub sprite: i32,
     pub name: String,
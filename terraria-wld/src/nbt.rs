@@ -0,0 +1,154 @@
+//! Reader for the NBT format tModLoader uses for its save data
+//!
+//! Same as Minecraft's NBT: big endian, every tag prefixed with its type,
+//! strings prefixed with their u16 length.
+
+use byteorder::{ReadBytesExt, BE};
+use std::collections::HashMap;
+use std::io::{self, Read};
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<u8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(HashMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    /// Look up a field of a compound tag
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(fields) => fields.get(key),
+            _ => None,
+        }
+    }
+    /// The value of any integer tag that fits into an i64
+    pub fn as_int(&self) -> Option<i64> {
+        match *self {
+            Tag::Byte(v) => Some(v.into()),
+            Tag::Short(v) => Some(v.into()),
+            Tag::Int(v) => Some(v.into()),
+            Tag::Long(v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(s) => Some(s),
+            _ => None,
+        }
+    }
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(tags) => Some(tags),
+            _ => None,
+        }
+    }
+}
+
+/// Read a named root tag, returning its value. The name is usually empty.
+pub fn read_root(f: &mut impl Read) -> io::Result<Tag> {
+    let type_ = f.read_u8()?;
+    let _name = read_string(f)?;
+    read_payload(f, type_)
+}
+
+fn read_payload(f: &mut impl Read, type_: u8) -> io::Result<Tag> {
+    Ok(match type_ {
+        1 => Tag::Byte(f.read_i8()?),
+        2 => Tag::Short(f.read_i16::<BE>()?),
+        3 => Tag::Int(f.read_i32::<BE>()?),
+        4 => Tag::Long(f.read_i64::<BE>()?),
+        5 => Tag::Float(f.read_f32::<BE>()?),
+        6 => Tag::Double(f.read_f64::<BE>()?),
+        7 => {
+            let mut buf = vec![0; read_len(f)?];
+            f.read_exact(&mut buf)?;
+            Tag::ByteArray(buf)
+        }
+        8 => Tag::String(read_string(f)?),
+        9 => {
+            let elem_type = f.read_u8()?;
+            let len = read_len(f)?;
+            let mut tags = Vec::with_capacity(len.min(4096));
+            for _ in 0..len {
+                tags.push(read_payload(f, elem_type)?);
+            }
+            Tag::List(tags)
+        }
+        10 => {
+            let mut fields = HashMap::new();
+            loop {
+                let type_ = f.read_u8()?;
+                if type_ == 0 {
+                    break;
+                }
+                let name = read_string(f)?;
+                fields.insert(name, read_payload(f, type_)?);
+            }
+            Tag::Compound(fields)
+        }
+        11 => {
+            let len = read_len(f)?;
+            let mut values = Vec::with_capacity(len.min(4096));
+            for _ in 0..len {
+                values.push(f.read_i32::<BE>()?);
+            }
+            Tag::IntArray(values)
+        }
+        12 => {
+            let len = read_len(f)?;
+            let mut values = Vec::with_capacity(len.min(4096));
+            for _ in 0..len {
+                values.push(f.read_i64::<BE>()?);
+            }
+            Tag::LongArray(values)
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown NBT tag type: {}", type_),
+            ))
+        }
+    })
+}
+
+fn read_len(f: &mut impl Read) -> io::Result<usize> {
+    let len = f.read_i32::<BE>()?;
+    usize::try_from(len).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Negative length"))
+}
+
+fn read_string(f: &mut impl Read) -> io::Result<String> {
+    let len = f.read_u16::<BE>()?;
+    let mut buf = vec![0; usize::from(len)];
+    f.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+#[test]
+fn test_read_root() {
+    #[rustfmt::skip]
+    let data = [
+        10, 0, 0, // root compound without name
+        8, 0, 3, b'm', b'o', b'd', 0, 2, b'E', b'x', // mod: "Ex"
+        9, 0, 5, b's', b'l', b'o', b't', b's', 2, 0, 0, 0, 2, 0, 1, 0, 7, // slots: [1s, 7s]
+        0,
+    ];
+    let root = read_root(&mut &data[..]).unwrap();
+    assert_eq!(root.get("mod").and_then(Tag::as_str), Some("Ex"));
+    let slots = root.get("slots").and_then(Tag::as_list).unwrap();
+    assert_eq!(
+        slots.iter().map(|t| t.as_int()).collect::<Vec<_>>(),
+        [Some(1), Some(7)]
+    );
+}
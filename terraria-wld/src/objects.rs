@@ -0,0 +1,197 @@
+//! Multi-tile objects, like chests, altars and statues
+//!
+//! Every tile of an object is stored separately in the world, with only its frame offset
+//! telling which part of which object it is. Frames are 18 pixels per tile, and the
+//! different styles of an object (like the kinds of chests) are laid out next to each
+//! other in the tile sheet, either horizontally or vertically.
+
+use crate::{read_tiles, BaseHeader, Tile};
+use std::error::Error;
+use std::fs::File;
+
+/// Size of a tile in the frame, including the 2 pixel padding
+const FRAME_TILE_SIZE: u16 = 18;
+
+/// How an object is laid out in its tile sheet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectShape {
+    pub width: u16,
+    pub height: u16,
+    /// Styles are stacked vertically in the tile sheet, instead of side by side
+    pub styles_vertical: bool,
+    /// How many styles fit in a row of the tile sheet before the next ones wrap to a new
+    /// row, 0 if they never wrap
+    pub styles_per_row: u16,
+}
+
+const fn shape(width: u16, height: u16) -> ObjectShape {
+    ObjectShape {
+        width,
+        height,
+        styles_vertical: false,
+        styles_per_row: 0,
+    }
+}
+
+const fn shape_v(width: u16, height: u16) -> ObjectShape {
+    ObjectShape {
+        styles_vertical: true,
+        ..shape(width, height)
+    }
+}
+
+const fn wrapped(shape: ObjectShape, styles_per_row: u16) -> ObjectShape {
+    ObjectShape {
+        styles_per_row,
+        ..shape
+    }
+}
+
+/// The shape of objects made of the tile `id`, if it's a known object
+pub fn object_shape(id: u16) -> Option<ObjectShape> {
+    Some(match id {
+        // Closed door
+        10 => shape_v(1, 3),
+        // Life Crystal
+        12 => shape(2, 2),
+        // Table
+        14 => shape(3, 2),
+        // Anvils, work bench
+        16 | 18 | 134 => shape(2, 1),
+        // Crafting stations, campfire
+        17 | 77 | 114 | 133 | 215 | 237 | 377 => shape(3, 2),
+        // Chests
+        21 | 467 => shape(2, 2),
+        // Demon/Crimson Altar
+        26 => shape(3, 2),
+        // Pots
+        28 => shape_v(2, 2),
+        // Shadow Orb/Crimson Heart
+        31 => shape(2, 2),
+        // Dresser
+        88 => shape(3, 2),
+        // Keg, cooking pot, crystal ball, crates
+        94 | 96 | 125 | 376 => shape(2, 2),
+        // Bookcase
+        101 => shape(3, 4),
+        // Grandfather clock
+        104 => shape(2, 5),
+        // Statues
+        105 => wrapped(shape(2, 3), 55),
+        // Sawmill, tables, manipulator
+        106 | 354 | 355 | 412 => shape(3, 3),
+        // Music boxes
+        139 => shape_v(2, 2),
+        // Exposed gems
+        178 => shape(1, 1),
+        // Large piles
+        186 | 187 => shape(3, 2),
+        // Water fountains
+        207 => shape(2, 4),
+        // Metal bars
+        239 => shape(1, 1),
+        // Pylons
+        597 => shape(3, 4),
+        _ => return None,
+    })
+}
+
+/// A multi-tile object in the world
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Object {
+    pub tile_id: u16,
+    /// Top left corner
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+    /// Which variant of the object this is, like the kind of chest or statue
+    pub style: u16,
+    /// Frame offset of the top left tile
+    pub frame_x: u16,
+    pub frame_y: u16,
+}
+
+impl Object {
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+}
+
+/// The style of the object the tile is part of, if it's a known object
+pub fn object_style(tile: &Tile) -> Option<u16> {
+    let shape = object_shape(tile.front?)?;
+    let frame = tile.frame?;
+    let (col, row) = (frame.x / FRAME_TILE_SIZE, frame.y / FRAME_TILE_SIZE);
+    Some(if shape.styles_vertical {
+        row / shape.height
+    } else {
+        row / shape.height * shape.styles_per_row + col / shape.width
+    })
+}
+
+/// The object that the tile at `x`, `y` is part of, if it's a known object
+pub fn object_at(tile: &Tile, x: u16, y: u16) -> Option<Object> {
+    let id = tile.front?;
+    let frame = tile.frame?;
+    let shape = object_shape(id)?;
+    let (col, row) = (frame.x / FRAME_TILE_SIZE, frame.y / FRAME_TILE_SIZE);
+    let (offset_x, offset_y) = (col % shape.width, row % shape.height);
+    let style = object_style(tile)?;
+    Some(Object {
+        tile_id: id,
+        x: x.checked_sub(offset_x)?,
+        y: y.checked_sub(offset_y)?,
+        width: shape.width,
+        height: shape.height,
+        style,
+        frame_x: frame.x - offset_x * FRAME_TILE_SIZE,
+        frame_y: frame.y - offset_y * FRAME_TILE_SIZE,
+    })
+}
+
+/// Read every known object whose tile id passes `filter`, in tile order
+pub fn read_objects(
+    file: &File,
+    base_header: &BaseHeader,
+    mut filter: impl FnMut(u16) -> bool,
+) -> Result<Vec<Object>, Box<dyn Error>> {
+    let mut objects = Vec::new();
+    read_tiles(file, base_header, |tile, x, y| {
+        if !tile.front.is_some_and(&mut filter) {
+            return;
+        }
+        // Only count each object once, at its top left tile
+        if let Some(object) = object_at(&tile, x, y) {
+            if object.x == x && object.y == y {
+                objects.push(object);
+            }
+        }
+    })?;
+    Ok(objects)
+}
+
+#[test]
+fn test_object_at() {
+    use crate::TileFrameOffset;
+    let tile = |id, x, y| Tile {
+        front: Some(id),
+        frame: Some(TileFrameOffset { x, y }),
+        ..Default::default()
+    };
+    // Bottom right tile of a gold chest
+    let chest = object_at(&tile(21, 54, 18), 101, 51).unwrap();
+    assert_eq!(
+        (chest.x, chest.y, chest.style, chest.frame_x),
+        (100, 50, 1, 36)
+    );
+    // Middle tile of the second style of pot
+    let pot = object_at(&tile(28, 18, 54), 11, 21).unwrap();
+    assert_eq!((pot.x, pot.y, pot.style), (10, 20, 1));
+    // Bottom left tile of the second statue on the second row of the sheet
+    let statue = object_at(&tile(105, 36, 90), 30, 42).unwrap();
+    assert_eq!((statue.x, statue.y, statue.style), (30, 40, 56));
+    assert!(object_at(&tile(1, 0, 0), 0, 0).is_none());
+}
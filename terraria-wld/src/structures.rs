@@ -0,0 +1,332 @@
+//! Finding world structures, like the dungeon or floating islands
+//!
+//! Every tile is marked with the structure its block or wall belongs to, and touching
+//! tiles with the same mark are grouped together. Groups that are too small to be a
+//! real structure, like a single marble block, are left out.
+
+use crate::{read_header, read_tiles, BaseHeader, Header, Liquid, Tile, XSide, OCEAN_WIDTH};
+use std::error::Error;
+use std::fs::File;
+use terraria_ids::{TileId, WallId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StructureKind {
+    Dungeon,
+    JungleTemple,
+    BeeHive,
+    SpiderCave,
+    MarbleCave,
+    GraniteCave,
+    Pyramid,
+    LivingTree,
+    FloatingIsland,
+    ShimmerLake,
+    Ocean,
+}
+
+impl StructureKind {
+    pub const ALL: [StructureKind; 11] = [
+        StructureKind::Dungeon,
+        StructureKind::JungleTemple,
+        StructureKind::BeeHive,
+        StructureKind::SpiderCave,
+        StructureKind::MarbleCave,
+        StructureKind::GraniteCave,
+        StructureKind::Pyramid,
+        StructureKind::LivingTree,
+        StructureKind::FloatingIsland,
+        StructureKind::ShimmerLake,
+        StructureKind::Ocean,
+    ];
+    pub fn name(self) -> &'static str {
+        match self {
+            StructureKind::Dungeon => "dungeon",
+            StructureKind::JungleTemple => "jungle temple",
+            StructureKind::BeeHive => "bee hive",
+            StructureKind::SpiderCave => "spider cave",
+            StructureKind::MarbleCave => "marble cave",
+            StructureKind::GraniteCave => "granite cave",
+            StructureKind::Pyramid => "pyramid",
+            StructureKind::LivingTree => "living tree",
+            StructureKind::FloatingIsland => "floating island",
+            StructureKind::ShimmerLake => "shimmer lake",
+            StructureKind::Ocean => "ocean",
+        }
+    }
+    /// Groups with fewer tiles than this aren't counted as a structure
+    fn min_tiles(self) -> u32 {
+        match self {
+            StructureKind::Dungeon | StructureKind::JungleTemple => 1000,
+            StructureKind::Ocean => 1000,
+            StructureKind::MarbleCave | StructureKind::GraniteCave => 300,
+            StructureKind::Pyramid | StructureKind::SpiderCave => 200,
+            StructureKind::BeeHive | StructureKind::LivingTree => 150,
+            StructureKind::ShimmerLake => 50,
+            StructureKind::FloatingIsland => 30,
+        }
+    }
+    /// There's only one of these per world, so only the biggest group is kept
+    fn is_unique(self) -> bool {
+        matches!(self, StructureKind::Dungeon | StructureKind::JungleTemple)
+    }
+}
+
+/// A structure with its bounding rectangle in tiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Structure {
+    pub kind: StructureKind,
+    /// Top left corner
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+    /// Number of tiles that make up the structure
+    pub tiles: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DungeonColor {
+    Blue,
+    Green,
+    Pink,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DungeonInfo {
+    pub side: XSide,
+    pub color: DungeonColor,
+    /// Where the Old Man stands, in front of the entrance
+    pub entrance_x: i32,
+    pub entrance_y: i32,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Structures {
+    /// `None` if no dungeon bricks were found
+    pub dungeon: Option<DungeonInfo>,
+    /// Sorted by kind, then from west to east
+    pub structures: Vec<Structure>,
+}
+
+/// Which structure a tile belongs to, going by its block, wall and liquid
+fn structure_of(tile: &Tile, x: u16, y: u16, header: &Header) -> Option<StructureKind> {
+    let above_surface = f64::from(y) < header.surface_y;
+    let kind_of_block = |id| match TileId(id) {
+        TileId::BLUE_DUNGEON_BRICK | TileId::GREEN_DUNGEON_BRICK | TileId::PINK_DUNGEON_BRICK => {
+            Some(StructureKind::Dungeon)
+        }
+        TileId::LIHZAHRD_BRICK => Some(StructureKind::JungleTemple),
+        TileId::HIVE => Some(StructureKind::BeeHive),
+        TileId::MARBLE => Some(StructureKind::MarbleCave),
+        TileId::GRANITE => Some(StructureKind::GraniteCave),
+        TileId::SANDSTONE_BRICK => Some(StructureKind::Pyramid),
+        TileId::LIVING_WOOD
+        | TileId::LEAF_BLOCK
+        | TileId::LIVING_MAHOGANY
+        | TileId::LIVING_MAHOGANY_LEAVES => Some(StructureKind::LivingTree),
+        TileId::CLOUD | TileId::RAIN_CLOUD | TileId::SNOW_CLOUD | TileId::SUNPLATE
+            if above_surface =>
+        {
+            Some(StructureKind::FloatingIsland)
+        }
+        _ => None,
+    };
+    let kind_of_wall = |id| match WallId(id) {
+        WallId::BLUE_DUNGEON_UNSAFE
+        | WallId::GREEN_DUNGEON_UNSAFE
+        | WallId::PINK_DUNGEON_UNSAFE
+        | WallId::BLUE_DUNGEON_SLAB_UNSAFE
+        | WallId::BLUE_DUNGEON_TILE_UNSAFE
+        | WallId::PINK_DUNGEON_SLAB_UNSAFE
+        | WallId::PINK_DUNGEON_TILE_UNSAFE
+        | WallId::GREEN_DUNGEON_SLAB_UNSAFE
+        | WallId::GREEN_DUNGEON_TILE_UNSAFE => Some(StructureKind::Dungeon),
+        WallId::LIHZAHRD_BRICK_UNSAFE => Some(StructureKind::JungleTemple),
+        WallId::HIVE_UNSAFE => Some(StructureKind::BeeHive),
+        WallId::SPIDER_UNSAFE => Some(StructureKind::SpiderCave),
+        WallId::MARBLE_UNSAFE => Some(StructureKind::MarbleCave),
+        WallId::GRANITE_UNSAFE => Some(StructureKind::GraniteCave),
+        WallId::SANDSTONE_BRICK => Some(StructureKind::Pyramid),
+        WallId::LIVING_WOOD_UNSAFE => Some(StructureKind::LivingTree),
+        WallId::CLOUD | WallId::DISC_WALL if above_surface => Some(StructureKind::FloatingIsland),
+        _ => None,
+    };
+    tile.front
+        .and_then(kind_of_block)
+        .or_else(|| tile.back.and_then(kind_of_wall))
+        .or(match tile.liquid {
+            Some(Liquid::Shimmer) => Some(StructureKind::ShimmerLake),
+            Some(Liquid::Water)
+                if f64::from(y) < header.rock_y
+                    && (x < OCEAN_WIDTH || x >= header.width.saturating_sub(OCEAN_WIDTH)) =>
+            {
+                Some(StructureKind::Ocean)
+            }
+            _ => None,
+        })
+}
+
+/// The color of dungeon bricks and walls
+fn dungeon_color(tile: &Tile) -> Option<DungeonColor> {
+    match (tile.front.map(TileId), tile.back.map(WallId)) {
+        (Some(TileId::BLUE_DUNGEON_BRICK), _)
+        | (
+            _,
+            Some(
+                WallId::BLUE_DUNGEON_UNSAFE
+                | WallId::BLUE_DUNGEON_SLAB_UNSAFE
+                | WallId::BLUE_DUNGEON_TILE_UNSAFE,
+            ),
+        ) => Some(DungeonColor::Blue),
+        (Some(TileId::GREEN_DUNGEON_BRICK), _)
+        | (
+            _,
+            Some(
+                WallId::GREEN_DUNGEON_UNSAFE
+                | WallId::GREEN_DUNGEON_SLAB_UNSAFE
+                | WallId::GREEN_DUNGEON_TILE_UNSAFE,
+            ),
+        ) => Some(DungeonColor::Green),
+        (Some(TileId::PINK_DUNGEON_BRICK), _)
+        | (
+            _,
+            Some(
+                WallId::PINK_DUNGEON_UNSAFE
+                | WallId::PINK_DUNGEON_SLAB_UNSAFE
+                | WallId::PINK_DUNGEON_TILE_UNSAFE,
+            ),
+        ) => Some(DungeonColor::Pink),
+        _ => None,
+    }
+}
+
+pub fn find_structures(
+    file: &File,
+    base_header: &BaseHeader,
+) -> Result<Structures, Box<dyn Error>> {
+    let header = read_header(file, base_header.offsets.header as u64)?;
+    let (width, height) = (usize::from(header.width), usize::from(header.height));
+    // Index of the structure kind + 1 for every tile, 0 if it's not part of one
+    let mut marks = vec![0u8; width * height];
+    let mut color_counts = [0u32; 3];
+    read_tiles(file, base_header, |tile, x, y| {
+        if let Some(kind) = structure_of(&tile, x, y, &header) {
+            marks[usize::from(y) * width + usize::from(x)] = kind as u8 + 1;
+        }
+        if let Some(color) = dungeon_color(&tile) {
+            color_counts[color as usize] += 1;
+        }
+    })?;
+    let structures = group_marks(&mut marks, header.width, header.height);
+    let dungeon = structures
+        .iter()
+        .any(|s| s.kind == StructureKind::Dungeon)
+        .then(|| {
+            let colors = [DungeonColor::Blue, DungeonColor::Green, DungeonColor::Pink];
+            let (color, _) = colors
+                .iter()
+                .zip(color_counts)
+                .max_by_key(|&(_, count)| count)
+                .unwrap();
+            DungeonInfo {
+                side: if header.dungeon_x < i32::from(header.width) / 2 {
+                    XSide::West
+                } else {
+                    XSide::East
+                },
+                color: *color,
+                entrance_x: header.dungeon_x,
+                entrance_y: header.dungeon_y,
+            }
+        });
+    Ok(Structures {
+        dungeon,
+        structures,
+    })
+}
+
+/// Group touching marked tiles into structures, clearing the marks on the way
+fn group_marks(marks: &mut [u8], width: u16, height: u16) -> Vec<Structure> {
+    let (w, h) = (usize::from(width), usize::from(height));
+    let mut structures: Vec<Structure> = Vec::new();
+    let mut stack = Vec::new();
+    for start in 0..marks.len() {
+        let mark = marks[start];
+        if mark == 0 {
+            continue;
+        }
+        let kind = StructureKind::ALL[usize::from(mark - 1)];
+        marks[start] = 0;
+        stack.push(start);
+        let (mut left, mut top, mut right, mut bottom) = (w, h, 0, 0);
+        let mut tiles = 0;
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % w, i / w);
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x);
+            bottom = bottom.max(y);
+            tiles += 1;
+            // Diagonal neighbours count as touching too
+            for ny in y.saturating_sub(1)..=(y + 1).min(h - 1) {
+                for nx in x.saturating_sub(1)..=(x + 1).min(w - 1) {
+                    let n = ny * w + nx;
+                    if marks[n] == mark {
+                        marks[n] = 0;
+                        stack.push(n);
+                    }
+                }
+            }
+        }
+        if tiles < kind.min_tiles() {
+            continue;
+        }
+        let structure = Structure {
+            kind,
+            x: left as u16,
+            y: top as u16,
+            width: (right - left + 1) as u16,
+            height: (bottom - top + 1) as u16,
+            tiles,
+        };
+        if kind.is_unique() {
+            if let Some(existing) = structures.iter_mut().find(|s| s.kind == kind) {
+                if existing.tiles < tiles {
+                    *existing = structure;
+                }
+                continue;
+            }
+        }
+        structures.push(structure);
+    }
+    structures.sort_by_key(|s| (s.kind as u8, s.x, s.y));
+    structures
+}
+
+#[test]
+fn test_group_marks() {
+    let island = StructureKind::FloatingIsland as u8 + 1;
+    let (width, height) = (40, 40);
+    let mut marks = vec![0u8; 40 * 40];
+    // A 10x4 island, diagonally touching a 5x2 one, and a single tile too small to count
+    for y in 2..6 {
+        for x in 3..13 {
+            marks[y * 40 + x] = island;
+        }
+    }
+    for y in 6..8 {
+        for x in 13..18 {
+            marks[y * 40 + x] = island;
+        }
+    }
+    marks[30 * 40 + 30] = island;
+    let structures = group_marks(&mut marks, width, height);
+    assert_eq!(structures.len(), 1);
+    let s = structures[0];
+    assert_eq!((s.x, s.y, s.width, s.height, s.tiles), (3, 2, 15, 6, 50));
+}
@@ -0,0 +1,223 @@
+//! tModLoader support
+//!
+//! tModLoader keeps everything modded in a `.twld` file next to the `.wld`, as gzipped NBT.
+//! Modded items are left out of the vanilla chests, so the chest contents have to be merged
+//! back together from both files.
+
+use crate::nbt::{self, Tag};
+use crate::{Chest, Item};
+use flate2::read::GzDecoder;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+/// The mod name tModLoader uses for vanilla items that need extra data saved
+pub const VANILLA_MOD_NAME: &str = "Terraria";
+
+/// An item saved by tModLoader, identified by its mod and internal name
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModdedItem {
+    pub slot: u8,
+    pub mod_name: String,
+    pub name: String,
+    pub stack: u16,
+    /// Vanilla prefix, 0 if none
+    pub prefix_id: u8,
+    /// Full name of a modded prefix, like `ExampleMod/Awesome`
+    pub mod_prefix: Option<String>,
+}
+
+impl ModdedItem {
+    /// The name tModLoader uses to refer to the item, like `ExampleMod/ExampleSword`
+    pub fn full_name(&self) -> String {
+        format!("{}/{}", self.mod_name, self.name)
+    }
+    /// Whether this is a vanilla item, in which case `name` is its internal name
+    pub fn is_vanilla(&self) -> bool {
+        self.mod_name == VANILLA_MOD_NAME
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModdedChest {
+    pub x: u16,
+    pub y: u16,
+    pub items: Vec<ModdedItem>,
+    /// Vanilla items tModLoader saved by id with their slots, because they have modded
+    /// data attached
+    pub vanilla_items: Vec<(u8, Item)>,
+}
+
+/// The contents of a `.twld` file we care about
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModData {
+    pub chests: Vec<ModdedChest>,
+    /// The complete NBT data, for everything that isn't interpreted by this crate
+    pub root: Tag,
+}
+
+/// The path of the `.twld` file belonging to a world
+pub fn twld_path(wld_path: &Path) -> PathBuf {
+    wld_path.with_extension("twld")
+}
+
+pub fn read_twld(path: &Path) -> Result<ModData, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let root = nbt::read_root(&mut GzDecoder::new(BufReader::new(file)))?;
+    read_mod_data(root)
+}
+
+fn read_mod_data(root: Tag) -> Result<ModData, Box<dyn Error>> {
+    let mut chests = Vec::new();
+    for chest in root
+        .get("chests")
+        .and_then(Tag::as_list)
+        .unwrap_or_default()
+    {
+        let pos = |key| chest.get(key).and_then(Tag::as_int).map(|v| v as u16);
+        let (x, y) = match (pos("x"), pos("y")) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return Err("Modded chest without position".into()),
+        };
+        let mut items = Vec::new();
+        let mut vanilla_items = Vec::new();
+        for tag in chest
+            .get("items")
+            .and_then(Tag::as_list)
+            .unwrap_or_default()
+        {
+            if let Some(item) = read_vanilla_item(tag) {
+                vanilla_items.push(item);
+            } else if let Some(item) = read_item(tag) {
+                items.push(item);
+            }
+        }
+        chests.push(ModdedChest {
+            x,
+            y,
+            items,
+            vanilla_items,
+        });
+    }
+    Ok(ModData { chests, root })
+}
+
+/// Read a vanilla item saved with its id instead of a name, with its slot
+fn read_vanilla_item(tag: &Tag) -> Option<(u8, Item)> {
+    if tag.get("mod").and_then(Tag::as_str) != Some(VANILLA_MOD_NAME) || tag.get("name").is_some() {
+        return None;
+    }
+    let int_field = |key| tag.get(key).and_then(Tag::as_int);
+    let item = Item {
+        id: i32::try_from(int_field("id")?).ok()?,
+        stack: int_field("stack").unwrap_or(1) as u16,
+        prefix_id: int_field("prefix").unwrap_or(0) as u8,
+    };
+    Some((int_field("slot")? as u8, item))
+}
+
+fn read_item(tag: &Tag) -> Option<ModdedItem> {
+    let str_field = |tag: &Tag, key| tag.get(key).and_then(Tag::as_str).map(str::to_owned);
+    let mut mod_name = str_field(tag, "mod")?;
+    let mut name = str_field(tag, "name")?;
+    // Items of mods that are no longer loaded remember what they were in their data
+    if mod_name == "ModLoader" && name == "UnloadedItem" {
+        if let Some(data) = tag.get("data") {
+            mod_name = str_field(data, "mod").unwrap_or(mod_name);
+            name = str_field(data, "name").unwrap_or(name);
+        }
+    }
+    let mod_prefix = match (
+        str_field(tag, "modPrefixMod"),
+        str_field(tag, "modPrefixName"),
+    ) {
+        (Some(m), Some(n)) => Some(format!("{}/{}", m, n)),
+        _ => None,
+    };
+    let int_field = |key| tag.get(key).and_then(Tag::as_int);
+    Some(ModdedItem {
+        slot: int_field("slot")? as u8,
+        mod_name,
+        name,
+        // A missing stack means a single item
+        stack: int_field("stack").unwrap_or(1) as u16,
+        prefix_id: int_field("prefix").unwrap_or(0) as u8,
+        mod_prefix,
+    })
+}
+
+impl ModData {
+    /// Put the modded items into the chests they belong to.
+    ///
+    /// The vanilla item in a slot that has a modded item is cleared, since it's
+    /// only a placeholder. Vanilla items saved by tModLoader replace the placeholder.
+    pub fn merge_chests(&self, chests: &mut [Chest]) {
+        for modded in &self.chests {
+            let chest = match chests
+                .iter_mut()
+                .find(|c| c.x == modded.x && c.y == modded.y)
+            {
+                Some(chest) => chest,
+                None => continue,
+            };
+            for item in &modded.items {
+                if let Some(vanilla) = chest.items.get_mut(usize::from(item.slot)) {
+                    *vanilla = Default::default();
+                }
+                chest.modded_items.retain(|it| it.slot != item.slot);
+                chest.modded_items.push(item.clone());
+            }
+            for &(slot, item) in &modded.vanilla_items {
+                if let Some(vanilla) = chest.items.get_mut(usize::from(slot)) {
+                    *vanilla = item;
+                    chest.modded_items.retain(|it| it.slot != slot);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_vanilla_item_by_id() {
+    #[rustfmt::skip]
+    let data = [
+        10, 0, 0, // root compound without name
+        9, 0, 6, b'c', b'h', b'e', b's', b't', b's', 10, 0, 0, 0, 1, // chests: [1 compound]
+        3, 0, 1, b'x', 0, 0, 0, 10, // x: 10
+        3, 0, 1, b'y', 0, 0, 0, 20, // y: 20
+        9, 0, 5, b'i', b't', b'e', b'm', b's', 10, 0, 0, 0, 2, // items: [2 compounds]
+        // A vanilla item with modded data: mod: "Terraria", id: 29, stack: 2s, slot: 3b
+        8, 0, 3, b'm', b'o', b'd', 0, 8, b'T', b'e', b'r', b'r', b'a', b'r', b'i', b'a',
+        3, 0, 2, b'i', b'd', 0, 0, 0, 29,
+        2, 0, 5, b's', b't', b'a', b'c', b'k', 0, 2,
+        1, 0, 4, b's', b'l', b'o', b't', 3,
+        0,
+        // A modded item: mod: "Ex", name: "Sword", slot: 5b
+        8, 0, 3, b'm', b'o', b'd', 0, 2, b'E', b'x',
+        8, 0, 4, b'n', b'a', b'm', b'e', 0, 5, b'S', b'w', b'o', b'r', b'd',
+        1, 0, 4, b's', b'l', b'o', b't', 5,
+        0,
+        0, // end of chest
+        0, // end of root
+    ];
+    let mod_data = read_mod_data(nbt::read_root(&mut &data[..]).unwrap()).unwrap();
+    let mut chest = Chest {
+        x: 10,
+        y: 20,
+        name: String::new(),
+        items: [Item::default(); crate::CHEST_MAX_ITEMS as usize],
+        modded_items: Vec::new(),
+    };
+    // Placeholders in the vanilla chest
+    chest.items[3].id = 1;
+    chest.items[5].id = 1;
+    mod_data.merge_chests(std::slice::from_mut(&mut chest));
+    assert_eq!((chest.items[3].id, chest.items[3].stack), (29, 2));
+    assert_eq!(chest.items[5].id, 0);
+    assert_eq!(chest.modded_items.len(), 1);
+    assert_eq!(chest.modded_items[0].full_name(), "Ex/Sword");
+}
@@ -15,7 +15,7 @@ use egui_macroquad::{
 use macroquad::prelude::*;
 use recently_used_list::RecentlyUsedList;
 use serde::{Deserialize, Serialize};
-use terraria_wld::{BaseHeader, Chest, Header, Liquid, Tile};
+use terraria_wld::{BaseHeader, Chest, Header, Tile};
 
 #[derive(Serialize, Deserialize, Default)]
 struct Config {
@@ -26,6 +26,8 @@ struct Config {
     draw_center_marker: bool,
     #[serde(default)]
     load_tiles_at_start: bool,
+    #[serde(default)]
+    shade_unexplored: bool,
 }
 
 impl Config {
@@ -68,6 +70,8 @@ async fn main() -> anyhow::Result<()> {
     let mut cfg = Config::load_or_default()?;
     let mut show_ui = true;
     let mut tiles = Vec::new();
+    // The map of a player, with a texture darkening the parts they haven't explored
+    let mut player_map: Option<(terraria_map::Map, Texture2D)> = None;
     prevent_quit();
     if cfg.load_most_recent && let Some(most_recent) = cfg.recent_files.most_recent().cloned() && load_world(&most_recent, &mut world_base, &mut tiles, &mut map_tex) {
         cfg.recent_files.use_(most_recent);
@@ -98,23 +102,10 @@ async fn main() -> anyhow::Result<()> {
         if let Some(world_base) = &mut world_base {
             if let Some(tex) = map_tex {
                 let header = &world_base.header;
-                draw_texture_ex(
-                    tex,
-                    cam_x,
-                    cam_y,
-                    WHITE,
-                    DrawTextureParams {
-                        dest_size: Some(vec2(
-                            header.width as f32 * scale as f32,
-                            header.height as f32 * scale as f32,
-                        )),
-                        source: None,
-                        rotation: 0.0,
-                        flip_x: false,
-                        flip_y: false,
-                        pivot: None,
-                    },
-                );
+                draw_world_texture(tex, header, cam_x, cam_y, scale);
+                if cfg.shade_unexplored && let Some((map, fog_tex)) = &player_map && map.matches(header) {
+                    draw_world_texture(*fog_tex, header, cam_x, cam_y, scale);
+                }
             } else if let Ok((tiles_, img)) = receiver.try_recv() {
                 tiles = tiles_;
                 let tex = Texture2D::from_image(&img);
@@ -143,6 +134,15 @@ async fn main() -> anyhow::Result<()> {
                                 }
                                 ui.close_menu();
                             }
+                            if ui.button("Open player map").clicked() {
+                                if let Some(path) = rfd::FileDialog::new()
+                                    .add_filter("Player or map file", &["plr", "map"])
+                                    .pick_file()
+                                {
+                                    player_map = load_player_map(&path, world_base.as_ref());
+                                }
+                                ui.close_menu();
+                            }
                             ui.separator();
                             let mut used = None;
                             ui.menu_button("Recent", |ui| {
@@ -172,6 +172,7 @@ async fn main() -> anyhow::Result<()> {
                         });
                         ui.menu_button("View", |ui| {
                             ui.checkbox(&mut cfg.draw_center_marker, "Draw center marker");
+                            ui.checkbox(&mut cfg.shade_unexplored, "Shade unexplored areas");
                         });
                     });
                 });
@@ -239,7 +240,7 @@ async fn main() -> anyhow::Result<()> {
                                         "Game mode",
                                         format!(
                                             "{} ({})",
-                                            game_mode_name(world_base.header.game_mode),
+                                            world_base.header.game_mode_name(),
                                             world_base.header.game_mode
                                         )
                                     );
@@ -248,10 +249,29 @@ async fn main() -> anyhow::Result<()> {
                                             + tile_x as usize,
                                     ) {
                                         field!("Pointing at", format!("{}, {}", tile_x, tile_y));
+                                        if let Some((map, _)) = &player_map && map.matches(&world_base.header) {
+                                            field!(
+                                                "Explored",
+                                                map.explored(tile_x as u16, tile_y as u16)
+                                            );
+                                        }
                                         match tile.front {
                                             Some(id) => field!("Tile", id),
                                             None => field!("Tile", "[none]"),
                                         };
+                                        if let Some(object) = terraria_wld::objects::object_at(
+                                            tile,
+                                            tile_x as u16,
+                                            tile_y as u16,
+                                        ) {
+                                            field!(
+                                                "Object",
+                                                format!(
+                                                    "style {} at {}, {}",
+                                                    object.style, object.x, object.y
+                                                )
+                                            );
+                                        }
                                         match tile.back {
                                             Some(id) => field!("Wall", id),
                                             None => field!("Wall", "[none]"),
@@ -260,9 +280,7 @@ async fn main() -> anyhow::Result<()> {
                                             "Liquid",
                                             match tile.liquid {
                                                 None => "[none]",
-                                                Some(Liquid::Water) => "Water",
-                                                Some(Liquid::Honey) => "Honey",
-                                                Some(Liquid::Lava) => "Lava",
+                                                Some(liquid) => liquid.name(),
                                             }
                                         );
                                     }
@@ -409,6 +427,26 @@ macro field_macro($ui:expr, $macname:ident) {
     }}
 }
 
+fn draw_world_texture(tex: Texture2D, header: &Header, cam_x: f32, cam_y: f32, scale: i32) {
+    draw_texture_ex(
+        tex,
+        cam_x,
+        cam_y,
+        WHITE,
+        DrawTextureParams {
+            dest_size: Some(vec2(
+                header.width as f32 * scale as f32,
+                header.height as f32 * scale as f32,
+            )),
+            source: None,
+            rotation: 0.0,
+            flip_x: false,
+            flip_y: false,
+            pivot: None,
+        },
+    );
+}
+
 fn guid_to_hex(guid: &[u8; 16]) -> String {
     use std::fmt::Write;
 
@@ -429,8 +467,8 @@ fn load_tiles(file: &File, base_header: &BaseHeader, header: &Header) -> (Vec<Ti
     let mut n_read = 0;
     terraria_wld::read_tiles(file, base_header, |tile, x, y| {
         tiles[y as usize * header.width as usize + x as usize] = tile;
-        if let Some(color) = tile_color(&tile) {
-            image.set_pixel(x as u32, y as u32, color);
+        if let Some([r, g, b, a]) = terraria_render::tile_pixel(&tile, &Default::default()) {
+            image.set_pixel(x as u32, y as u32, Color::from_rgba(r, g, b, a));
         }
         n_read += 1;
     })
@@ -443,6 +481,46 @@ fn load_tiles(file: &File, base_header: &BaseHeader, header: &Header) -> (Vec<Ti
     (tiles, image)
 }
 
+/// Load the map of a player, either directly from a .map file, or the map the player
+/// has of the current world, given their .plr file.
+fn load_player_map(
+    path: &Path,
+    world_base: Option<&WorldBase>,
+) -> Option<(terraria_map::Map, Texture2D)> {
+    let map_path = match (path.extension(), world_base) {
+        (Some(ext), Some(world_base)) if ext == "plr" => {
+            terraria_map::player_map_path(path, &world_base.header)
+        }
+        _ => path.to_owned(),
+    };
+    match terraria_map::read_map(&map_path) {
+        Ok(map) => {
+            let tex = Texture2D::from_image(&fog_image(&map));
+            tex.set_filter(FilterMode::Nearest);
+            Some((map, tex))
+        }
+        Err(e) => {
+            rfd::MessageDialog::new()
+                .set_description(&format!("{}: {}", map_path.display(), e))
+                .show();
+            None
+        }
+    }
+}
+
+/// Transparent where the player has explored, dark elsewhere
+fn fog_image(map: &terraria_map::Map) -> Image {
+    let mut image = Image::gen_image_color(map.width, map.height, Color::from_rgba(0, 0, 0, 0));
+    for y in 0..map.height {
+        for x in 0..map.width {
+            if !map.explored(x, y) {
+                image.set_pixel(x as u32, y as u32, Color::from_rgba(0, 0, 0, 200));
+            }
+        }
+    }
+    image
+}
+
 fn load_world(
     path: &Path,
     world_base: &mut Option<WorldBase>,
@@ -474,119 +552,3 @@ fn load_world(
         }
     }
 }
-
-fn tile_color(tile: &Tile) -> Option<Color> {
-    if let Some(id) = tile.front {
-        Some(match id {
-            0 => BROWN,
-            1 => GRAY,
-            2 => GREEN,
-            3 => YELLOW,
-            4 => RED,
-            5 => BROWN,
-            // Iron/copper/etc
-            6 | 7 | 8 | 9 => ORANGE,
-            // Platform
-            19 => BROWN,
-            // Wood
-            30 => BROWN,
-            // Clay
-            40 => Color::from_rgba(154, 73, 40, 255),
-            // Dungeon brick
-            43 | 44 => Color::from_rgba(131, 0, 178, 255),
-            // Chest
-            21 => YELLOW,
-            // Cobweb
-            51 => Color::from_rgba(188, 175, 174, 255),
-            // Vine
-            52 => GREEN,
-            // Sand
-            53 | 112 | 116 | 234 => YELLOW,
-            // Ash
-            57 => DARKGRAY,
-            // Hellstone
-            58 => Color::from_rgba(168, 53, 17, 255),
-            // Mud
-            59 => Color::from_rgba(57, 36, 10, 255),
-            // Jungle grass
-            60 => DARKGREEN,
-            // Jungle vine
-            62 => DARKGREEN,
-            // Glowing mushroom stuff
-            70 | 71 | 72 => Color::from_rgba(56, 230, 255, 255),
-            // Hallowed grass
-            109 => Color::from_rgba(135, 234, 193, 255),
-            // Hallowed vine
-            115 => Color::from_rgba(45, 133, 126, 255),
-            // Pearlstone
-            117 => Color::from_rgba(162, 117, 137, 255),
-            // Wooden beam
-            124 => BROWN,
-            // Snow
-            147 => Color::from_rgba(202, 234, 252, 255),
-            // Ice
-            161 | 162 => Color::from_rgba(151, 165, 220, 255),
-            // Pink ice
-            164 => Color::from_rgba(194, 165, 220, 255),
-            // Living wood
-            191 => BROWN,
-            // Living leaf
-            192 => GREEN,
-            // Crimson grass
-            199 => Color::from_rgba(220, 89, 69, 255),
-            // Crimstone
-            203 => Color::from_rgba(127, 15, 0, 255),
-            // Crimson vines
-            205 => Color::from_rgba(176, 53, 30, 255),
-            // Hive
-            225 => ORANGE,
-            // Temple bricks
-            226 => Color::from_rgba(250, 95, 0, 255),
-            // Marble
-            367 => Color::from_rgba(172, 189, 191, 255),
-            // Granite
-            368 => Color::from_rgba(15, 18, 34, 255),
-            // Living mahogany
-            383 => BROWN,
-            // Living mahogany leaf
-            384 => GREEN,
-            // Sandstone
-            396 => Color::from_rgba(197, 116, 0, 255),
-            // Hardened sand
-            397 => Color::from_rgba(192, 160, 19, 255),
-            // Desert fossil
-            404 => BROWN,
-            _ => MAGENTA,
-        })
-    } else if let Some(liq) = tile.liquid {
-        Some(match liq {
-            terraria_wld::Liquid::Water => BLUE,
-            terraria_wld::Liquid::Lava => RED,
-            terraria_wld::Liquid::Honey => Color::from_rgba(216, 167, 0, 255),
-        })
-    } else {
-        tile.back.map(|back| match back {
-            // Stone
-            1 => DARKGRAY,
-            // Dirt
-            2 => DARKBROWN,
-            // Wood
-            4 | 78 => DARKBROWN,
-            // Dungeon
-            7 | 8 | 9 | 17 | 18 | 19 | 94..=105 => DARKPURPLE,
-            // Crimson
-            83 => Color::from_rgba(59, 8, 8, 255),
-            _ => Color::from_rgba(180, 0, 180, 255),
-        })
-    }
-}
-
-fn game_mode_name(name: i32) -> &'static str {
-    match name {
-        0 => "Normal",
-        1 => "Expert",
-        2 => "Master",
-        3 => "Journey",
-        _ => "Unknown",
-    }
-}